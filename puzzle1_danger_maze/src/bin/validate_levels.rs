@@ -0,0 +1,81 @@
+// Checks level files without starting the game:
+//   cargo run --bin validate_levels -- [--root DIR] [--credits FILE]... [FILE]...
+// With no files, checks every level in the manifest. Exits with 1 if any
+// level has a problem, so it can be used in CI. Warnings don't count.
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::vec::Vec;
+
+use puzzle1_danger_maze::levels::manifest::{LevelManifest, DEFAULT_MANIFEST};
+use puzzle1_danger_maze::levels::validator;
+
+const USAGE: &str = "usage: validate_levels [--root DIR] [--credits FILE]... [FILE]...";
+
+// (file, is credits)
+fn manifest_files(root: &Path) -> Result<Vec::<(String, bool)>, String> {
+    let data = fs::read_to_string(root.join(DEFAULT_MANIFEST))
+        .map_err(|err| format!("{} could not be opened: {}", DEFAULT_MANIFEST, err))?;
+    let manifest: LevelManifest = serde_json::from_str(&data)
+        .map_err(|err| format!("{} is not a manifest: {}", DEFAULT_MANIFEST, err))?;
+    let mut files: Vec::<(String, bool)> = manifest.levels.iter().map(|entry| (entry.file.clone(), false)).collect();
+    if let Some(credits) = manifest.credits {
+        files.push((credits.file, true));
+    }
+    Ok(files)
+}
+
+fn main() -> ExitCode {
+    let mut root = PathBuf::from(".");
+    let mut files = Vec::<(String, bool)>::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--root" | "--credits" => {
+                let Some(value) = args.next() else {
+                    eprintln!("{}", USAGE);
+                    return ExitCode::from(2);
+                };
+                if arg == "--root" {
+                    root = PathBuf::from(value);
+                } else {
+                    files.push((value, true));
+                }
+            },
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return ExitCode::SUCCESS;
+            },
+            _ => files.push((arg, false))
+        }
+    }
+
+    if files.is_empty() {
+        files = match manifest_files(&root) {
+            Ok(files) => files,
+            Err(err) => {
+                eprintln!("{}", err);
+                return ExitCode::FAILURE;
+            }
+        };
+    }
+
+    let mut failed = 0;
+    for (file, credits) in files.iter() {
+        let issues = validator::check_file(&root, file, *credits);
+        let reasons: Vec::<String> = issues.iter().map(|issue| issue.to_string()).collect();
+        if issues.is_empty() {
+            println!("ok    {}", file);
+        } else if issues.iter().all(|issue| issue.is_warning()) {
+            println!("warn  {}: {}", file, reasons.join("; "));
+        } else {
+            failed += 1;
+            println!("FAIL  {}: {}", file, reasons.join("; "));
+        }
+    }
+
+    println!("{} of {} levels passed", files.len() - failed, files.len());
+    if failed > 0 { ExitCode::FAILURE } else { ExitCode::SUCCESS }
+}
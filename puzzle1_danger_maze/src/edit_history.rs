@@ -0,0 +1,472 @@
+use std::vec::Vec;
+
+use crate::game_components::{danger_bar::DangerBar, danger_block::DangerBlock, danger_circle::DangerCircle, danger_polygon::DangerPolygon, image::Image, wall::Wall};
+use crate::levels::level_model::{LevelBarModel, LevelBlockModel, LevelCircleModel, LevelImageModel, LevelMotionModel, LevelPolygonModel, LevelPulseModel, LevelWallModel};
+use crate::utils::{Circle, Point, Rect};
+
+// Everything in the level builder an edit can change
+pub struct EditTarget<'a> {
+    pub blocks: &'a mut Vec::<DangerBlock>,
+    pub circles: &'a mut Vec::<DangerCircle>,
+    pub bars: &'a mut Vec::<DangerBar>,
+    pub polygons: &'a mut Vec::<DangerPolygon>,
+    pub walls: &'a mut Vec::<Wall>,
+    pub images: &'a mut Vec::<Image>,
+    pub player: &'a mut Point<f64>,
+    pub goal: &'a mut Point<f64>
+}
+
+// One change made in the level builder, with enough detail to undo it
+#[derive(Clone, Debug, PartialEq)]
+pub enum EditCommand {
+    // New shapes always go on the end of the list
+    AddBlock(Rect<f64>),
+    AddCircle(Circle<f64>),
+    // Moved or resized. Whole models, as moving a shape takes its path along.
+    ChangeBlock { index: usize, before: LevelBlockModel, after: LevelBlockModel },
+    ChangeCircle { index: usize, before: LevelCircleModel, after: LevelCircleModel },
+    // Paths are kept with anything removed, so undo brings them back
+    DeleteBlock { index: usize, block: LevelBlockModel },
+    DeleteCircle { index: usize, circle: LevelCircleModel },
+    ChangeBlockMotion { index: usize, before: Option<LevelMotionModel>, after: Option<LevelMotionModel> },
+    ChangeCircleMotion { index: usize, before: Option<LevelMotionModel>, after: Option<LevelMotionModel> },
+    ChangeBlockPulse { index: usize, before: Option<LevelPulseModel>, after: Option<LevelPulseModel> },
+    ChangeCirclePulse { index: usize, before: Option<LevelPulseModel>, after: Option<LevelPulseModel> },
+    MovePlayer { before: Point<f64>, after: Point<f64> },
+    MoveGoal { before: Point<f64>, after: Point<f64> },
+    AddBar(LevelBarModel),
+    ChangeBar { index: usize, before: LevelBarModel, after: LevelBarModel },
+    DeleteBar { index: usize, bar: LevelBarModel },
+    AddPolygon(LevelPolygonModel),
+    ChangePolygon { index: usize, before: LevelPolygonModel, after: LevelPolygonModel },
+    DeletePolygon { index: usize, polygon: LevelPolygonModel },
+    AddWall(LevelWallModel),
+    ChangeWall { index: usize, before: LevelWallModel, after: LevelWallModel },
+    DeleteWall { index: usize, wall: LevelWallModel },
+    AddImage(LevelImageModel),
+    MoveImage { index: usize, before: Point<f64>, after: Point<f64> },
+    DeleteImage { index: usize, image: LevelImageModel },
+    ClearAll {
+        blocks: Vec::<LevelBlockModel>,
+        circles: Vec::<LevelCircleModel>,
+        bars: Vec::<LevelBarModel>,
+        polygons: Vec::<LevelPolygonModel>,
+        walls: Vec::<LevelWallModel>,
+        images: Vec::<LevelImageModel>
+    }
+}
+
+impl EditCommand {
+    pub fn apply(&self, target: &mut EditTarget) {
+        match self {
+            EditCommand::AddBlock(rect) => {
+                target.blocks.push(DangerBlock::new(rect.loc.x, rect.loc.y, rect.width, rect.height));
+            },
+            EditCommand::AddCircle(circle) => {
+                target.circles.push(DangerCircle::new(circle.loc.x, circle.loc.y, circle.size));
+            },
+            EditCommand::ChangeBlock { index, after, .. } => {
+                if let Some(block) = target.blocks.get_mut(*index) {
+                    *block = DangerBlock::from_model(after);
+                }
+            },
+            EditCommand::ChangeCircle { index, after, .. } => {
+                if let Some(circle) = target.circles.get_mut(*index) {
+                    *circle = DangerCircle::from_model(after);
+                }
+            },
+            EditCommand::DeleteBlock { index, .. } => {
+                if *index < target.blocks.len() {
+                    target.blocks.remove(*index);
+                }
+            },
+            EditCommand::DeleteCircle { index, .. } => {
+                if *index < target.circles.len() {
+                    target.circles.remove(*index);
+                }
+            },
+            EditCommand::ChangeBlockMotion { index, after, .. } => {
+                if let Some(block) = target.blocks.get_mut(*index) {
+                    block.anim.motion = after.clone();
+                }
+            },
+            EditCommand::ChangeCircleMotion { index, after, .. } => {
+                if let Some(circle) = target.circles.get_mut(*index) {
+                    circle.anim.motion = after.clone();
+                }
+            },
+            EditCommand::ChangeBlockPulse { index, after, .. } => {
+                if let Some(block) = target.blocks.get_mut(*index) {
+                    block.anim.pulse = *after;
+                }
+            },
+            EditCommand::ChangeCirclePulse { index, after, .. } => {
+                if let Some(circle) = target.circles.get_mut(*index) {
+                    circle.anim.pulse = *after;
+                }
+            },
+            EditCommand::MovePlayer { after, .. } => {
+                *target.player = *after;
+            },
+            EditCommand::MoveGoal { after, .. } => {
+                *target.goal = *after;
+            },
+            EditCommand::AddBar(bar) => {
+                target.bars.push(DangerBar::from_model(bar));
+            },
+            EditCommand::ChangeBar { index, after, .. } => {
+                if let Some(bar) = target.bars.get_mut(*index) {
+                    *bar = DangerBar::from_model(after);
+                }
+            },
+            EditCommand::DeleteBar { index, .. } => {
+                if *index < target.bars.len() {
+                    target.bars.remove(*index);
+                }
+            },
+            EditCommand::AddPolygon(polygon) => {
+                target.polygons.push(DangerPolygon::from_model(polygon));
+            },
+            EditCommand::ChangePolygon { index, after, .. } => {
+                if let Some(polygon) = target.polygons.get_mut(*index) {
+                    *polygon = DangerPolygon::from_model(after);
+                }
+            },
+            EditCommand::DeletePolygon { index, .. } => {
+                if *index < target.polygons.len() {
+                    target.polygons.remove(*index);
+                }
+            },
+            EditCommand::AddWall(wall) => {
+                target.walls.push(Wall::from_model(wall));
+            },
+            EditCommand::ChangeWall { index, after, .. } => {
+                if let Some(wall) = target.walls.get_mut(*index) {
+                    *wall = Wall::from_model(after);
+                }
+            },
+            EditCommand::DeleteWall { index, .. } => {
+                if *index < target.walls.len() {
+                    target.walls.remove(*index);
+                }
+            },
+            EditCommand::AddImage(image) => {
+                target.images.push(Image::new(image.filename.clone(), image.x, image.y));
+            },
+            EditCommand::MoveImage { index, after, .. } => {
+                if let Some(image) = target.images.get_mut(*index) {
+                    image.loc = *after;
+                }
+            },
+            EditCommand::DeleteImage { index, .. } => {
+                if *index < target.images.len() {
+                    target.images.remove(*index);
+                }
+            },
+            EditCommand::ClearAll { .. } => {
+                target.blocks.clear();
+                target.circles.clear();
+                target.bars.clear();
+                target.polygons.clear();
+                target.walls.clear();
+                target.images.clear();
+            }
+        }
+    }
+
+    pub fn revert(&self, target: &mut EditTarget) {
+        match self {
+            EditCommand::AddBlock(_) => {
+                target.blocks.pop();
+            },
+            EditCommand::AddCircle(_) => {
+                target.circles.pop();
+            },
+            EditCommand::ChangeBlock { index, before, .. } => {
+                if let Some(block) = target.blocks.get_mut(*index) {
+                    *block = DangerBlock::from_model(before);
+                }
+            },
+            EditCommand::ChangeCircle { index, before, .. } => {
+                if let Some(circle) = target.circles.get_mut(*index) {
+                    *circle = DangerCircle::from_model(before);
+                }
+            },
+            EditCommand::DeleteBlock { index, block } => {
+                target.blocks.insert((*index).min(target.blocks.len()), DangerBlock::from_model(block));
+            },
+            EditCommand::DeleteCircle { index, circle } => {
+                target.circles.insert((*index).min(target.circles.len()), DangerCircle::from_model(circle));
+            },
+            EditCommand::ChangeBlockMotion { index, before, .. } => {
+                if let Some(block) = target.blocks.get_mut(*index) {
+                    block.anim.motion = before.clone();
+                }
+            },
+            EditCommand::ChangeCircleMotion { index, before, .. } => {
+                if let Some(circle) = target.circles.get_mut(*index) {
+                    circle.anim.motion = before.clone();
+                }
+            },
+            EditCommand::ChangeBlockPulse { index, before, .. } => {
+                if let Some(block) = target.blocks.get_mut(*index) {
+                    block.anim.pulse = *before;
+                }
+            },
+            EditCommand::ChangeCirclePulse { index, before, .. } => {
+                if let Some(circle) = target.circles.get_mut(*index) {
+                    circle.anim.pulse = *before;
+                }
+            },
+            EditCommand::MovePlayer { before, .. } => {
+                *target.player = *before;
+            },
+            EditCommand::MoveGoal { before, .. } => {
+                *target.goal = *before;
+            },
+            EditCommand::AddBar(_) => {
+                target.bars.pop();
+            },
+            EditCommand::ChangeBar { index, before, .. } => {
+                if let Some(bar) = target.bars.get_mut(*index) {
+                    *bar = DangerBar::from_model(before);
+                }
+            },
+            EditCommand::DeleteBar { index, bar } => {
+                target.bars.insert((*index).min(target.bars.len()), DangerBar::from_model(bar));
+            },
+            EditCommand::AddPolygon(_) => {
+                target.polygons.pop();
+            },
+            EditCommand::ChangePolygon { index, before, .. } => {
+                if let Some(polygon) = target.polygons.get_mut(*index) {
+                    *polygon = DangerPolygon::from_model(before);
+                }
+            },
+            EditCommand::DeletePolygon { index, polygon } => {
+                target.polygons.insert((*index).min(target.polygons.len()), DangerPolygon::from_model(polygon));
+            },
+            EditCommand::AddWall(_) => {
+                target.walls.pop();
+            },
+            EditCommand::ChangeWall { index, before, .. } => {
+                if let Some(wall) = target.walls.get_mut(*index) {
+                    *wall = Wall::from_model(before);
+                }
+            },
+            EditCommand::DeleteWall { index, wall } => {
+                target.walls.insert((*index).min(target.walls.len()), Wall::from_model(wall));
+            },
+            EditCommand::AddImage(_) => {
+                target.images.pop();
+            },
+            EditCommand::MoveImage { index, before, .. } => {
+                if let Some(image) = target.images.get_mut(*index) {
+                    image.loc = *before;
+                }
+            },
+            EditCommand::DeleteImage { index, image } => {
+                let restored = Image::new(image.filename.clone(), image.x, image.y);
+                target.images.insert((*index).min(target.images.len()), restored);
+            },
+            EditCommand::ClearAll { blocks, circles, bars, polygons, walls, images } => {
+                *target.blocks = blocks.iter().map(DangerBlock::from_model).collect();
+                *target.circles = circles.iter().map(DangerCircle::from_model).collect();
+                *target.bars = bars.iter().map(DangerBar::from_model).collect();
+                *target.polygons = polygons.iter().map(DangerPolygon::from_model).collect();
+                *target.walls = walls.iter().map(Wall::from_model).collect();
+                *target.images = images.iter().map(|i| Image::new(i.filename.clone(), i.x, i.y)).collect();
+            }
+        }
+    }
+}
+
+// Undo and redo stacks. Edits are made by the builder as normal and then
+// recorded here, so a command is only applied again on redo.
+#[derive(Default)]
+pub struct EditHistory {
+    undo_stack: Vec::<EditCommand>,
+    redo_stack: Vec::<EditCommand>
+}
+
+impl EditHistory {
+    pub fn new() -> Self {
+        EditHistory::default()
+    }
+
+    // A fresh edit makes anything that was undone unreachable
+    pub fn record(&mut self, command: EditCommand) {
+        self.undo_stack.push(command);
+        self.redo_stack.clear();
+    }
+
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+
+    pub fn undo(&mut self, target: &mut EditTarget) -> bool {
+        match self.undo_stack.pop() {
+            Some(command) => {
+                command.revert(target);
+                self.redo_stack.push(command);
+                true
+            },
+            None => false
+        }
+    }
+
+    pub fn redo(&mut self, target: &mut EditTarget) -> bool {
+        match self.redo_stack.pop() {
+            Some(command) => {
+                command.apply(target);
+                self.undo_stack.push(command);
+                true
+            },
+            None => false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::levels::level_model::{LevelMotionKind, LevelPointModel};
+
+    struct Scene {
+        blocks: Vec::<DangerBlock>,
+        circles: Vec::<DangerCircle>,
+        bars: Vec::<DangerBar>,
+        polygons: Vec::<DangerPolygon>,
+        walls: Vec::<Wall>,
+        images: Vec::<Image>,
+        player: Point<f64>,
+        goal: Point<f64>
+    }
+
+    impl Scene {
+        fn new() -> Self {
+            Scene {
+                blocks: vec![DangerBlock::new(0.0, 0.0, 10.0, 10.0), DangerBlock::new(50.0, 50.0, 10.0, 10.0)],
+                circles: vec![DangerCircle::new(100.0, 100.0, 20.0)],
+                bars: vec![DangerBar::new(300.0, 300.0, 200.0, 20.0, 90.0, 0.0)],
+                polygons: vec![DangerPolygon::from_model(&LevelPolygonModel::new(&[(600.0, 600.0), (700.0, 600.0), (650.0, 700.0)]))],
+                walls: vec![Wall::new(800.0, 0.0, 20.0, 300.0)],
+                // Image needs a browser to load, so these tests leave it empty
+                images: Vec::new(),
+                player: Point::new(10.0, 10.0),
+                goal: Point::new(500.0, 500.0)
+            }
+        }
+
+        fn target(&mut self) -> EditTarget<'_> {
+            EditTarget {
+                blocks: &mut self.blocks,
+                circles: &mut self.circles,
+                bars: &mut self.bars,
+                polygons: &mut self.polygons,
+                walls: &mut self.walls,
+                images: &mut self.images,
+                player: &mut self.player,
+                goal: &mut self.goal
+            }
+        }
+
+        fn rects(&self) -> Vec::<Rect<f64>> {
+            self.blocks.iter().map(|b| b.pos).collect()
+        }
+    }
+
+    #[test]
+    fn undo_and_redo_a_delete() {
+        let mut scene = Scene::new();
+        let mut history = EditHistory::new();
+        let original = scene.rects();
+
+        let block = scene.blocks.remove(0).model();
+        history.record(EditCommand::DeleteBlock { index: 0, block });
+
+        assert!(history.undo(&mut scene.target()));
+        assert_eq!(scene.rects(), original);
+        assert!(history.redo(&mut scene.target()));
+        assert_eq!(scene.rects(), vec![Rect::new(50.0, 50.0, 10.0, 10.0)]);
+        assert!(!history.redo(&mut scene.target()));
+    }
+
+    #[test]
+    fn undo_a_sequence_in_reverse() {
+        let mut scene = Scene::new();
+        let mut history = EditHistory::new();
+
+        scene.player = Point::new(40.0, 40.0);
+        history.record(EditCommand::MovePlayer { before: Point::new(10.0, 10.0), after: scene.player });
+        scene.circles[0].pos = Circle::new(120.0, 100.0, 30.0);
+        history.record(EditCommand::ChangeCircle {
+            index: 0,
+            before: LevelCircleModel::new(100.0, 100.0, 20.0),
+            after: scene.circles[0].model()
+        });
+        history.record(EditCommand::ClearAll {
+            blocks: scene.blocks.iter().map(|b| b.model()).collect(),
+            circles: scene.circles.iter().map(|c| c.model()).collect(),
+            bars: scene.bars.iter().map(|b| b.model()).collect(),
+            polygons: scene.polygons.iter().map(|p| p.model()).collect(),
+            walls: scene.walls.iter().map(|w| w.model()).collect(),
+            images: Vec::new()
+        });
+        scene.blocks.clear();
+        scene.circles.clear();
+        scene.bars.clear();
+        scene.polygons.clear();
+        scene.walls.clear();
+
+        while history.undo(&mut scene.target()) {}
+        assert_eq!(scene.rects().len(), 2);
+        assert_eq!(scene.bars[0].model(), LevelBarModel::new(300.0, 300.0, 200.0, 20.0, 90.0, 0.0));
+        assert_eq!(scene.polygons[0].points.len(), 3);
+        assert_eq!(scene.walls[0].pos, Rect::new(800.0, 0.0, 20.0, 300.0));
+        assert_eq!(scene.circles[0].pos, Circle::new(100.0, 100.0, 20.0));
+        assert_eq!(scene.player, Point::new(10.0, 10.0));
+
+        while history.redo(&mut scene.target()) {}
+        assert!(scene.blocks.is_empty() && scene.circles.is_empty() && scene.bars.is_empty() && scene.polygons.is_empty() && scene.walls.is_empty());
+        assert_eq!(scene.player, Point::new(40.0, 40.0));
+    }
+
+    #[test]
+    fn new_edit_drops_redo() {
+        let mut scene = Scene::new();
+        let mut history = EditHistory::new();
+        scene.blocks.push(DangerBlock::new(1.0, 2.0, 3.0, 4.0));
+        history.record(EditCommand::AddBlock(Rect::new(1.0, 2.0, 3.0, 4.0)));
+        history.undo(&mut scene.target());
+        assert_eq!(scene.blocks.len(), 2);
+
+        history.record(EditCommand::MoveGoal { before: scene.goal, after: Point::new(1.0, 1.0) });
+        assert!(!history.redo(&mut scene.target()));
+        assert!(history.undo(&mut scene.target()));
+        assert_eq!(scene.blocks.len(), 2);
+    }
+
+    #[test]
+    fn undo_keeps_paths() {
+        let mut scene = Scene::new();
+        let mut history = EditHistory::new();
+        let motion = LevelMotionModel {
+            kind: LevelMotionKind::Orbit,
+            points: vec![LevelPointModel { x: 150.0, y: 100.0 }],
+            speed: 50.0,
+            phase: 0.0
+        };
+        scene.circles[0].anim.motion = Some(motion.clone());
+        history.record(EditCommand::ChangeCircleMotion { index: 0, before: None, after: Some(motion.clone()) });
+
+        let circle = scene.circles.remove(0).model();
+        history.record(EditCommand::DeleteCircle { index: 0, circle });
+        assert!(history.undo(&mut scene.target()));
+        assert_eq!(scene.circles[0].anim.motion, Some(motion));
+
+        assert!(history.undo(&mut scene.target()));
+        assert_eq!(scene.circles[0].anim.motion, None);
+    }
+}
@@ -0,0 +1,115 @@
+use std::f64::consts::PI;
+
+use wasm_bindgen::JsValue;
+use web_sys::CanvasRenderingContext2d;
+
+use crate::geometry::{self, OrientedRect};
+use crate::levels::level_model::LevelBarModel;
+use crate::utils::{Circle, Point};
+
+// Most a bar is allowed to turn between two tests of a swept hit, in radians
+const MAX_TURN: f64 = PI / 16.0;
+
+pub struct DangerBar {
+    pub pivot: Point<f64>,
+    pub length: f64,
+    pub thickness: f64,
+    // Degrees per second, clockwise on screen
+    pub speed: f64,
+    // Degrees at the start of the level
+    pub start_angle: f64,
+    // Radians, now and before the last update
+    angle: f64,
+    prev_angle: f64,
+    time: f64
+}
+
+impl DangerBar {
+    pub fn new(x: f64, y: f64, length: f64, thickness: f64, speed: f64, start_angle: f64) -> Self {
+        DangerBar {
+            pivot: Point::new(x, y),
+            length,
+            thickness,
+            speed,
+            start_angle,
+            angle: start_angle.to_radians(),
+            prev_angle: start_angle.to_radians(),
+            time: 0.0
+        }
+    }
+
+    pub fn from_model(model: &LevelBarModel) -> Self {
+        DangerBar::new(model.x, model.y, model.length, model.thickness, model.speed, model.angle)
+    }
+
+    pub fn model(&self) -> LevelBarModel {
+        LevelBarModel::new(self.pivot.x, self.pivot.y, self.length, self.thickness, self.speed, self.start_angle)
+    }
+
+    pub fn restart(&mut self) {
+        self.time = 0.0;
+        self.update(0.0);
+        self.prev_angle = self.angle;
+    }
+
+    // Where the bar is now
+    pub fn rect(&self) -> OrientedRect {
+        OrientedRect::new(self.pivot, self.length, self.thickness, self.angle)
+    }
+
+    // Where the bar is when the level starts, which is what the builder shows
+    pub fn start_rect(&self) -> OrientedRect {
+        OrientedRect::new(self.pivot, self.length, self.thickness, self.start_angle.to_radians())
+    }
+
+    pub fn update(&mut self, delta: f64) {
+        self.time += delta;
+        self.prev_angle = self.angle;
+        // Worked out from the total time so it never drifts
+        self.angle = (self.start_angle + self.speed * self.time / 1000.0).to_radians();
+    }
+
+    pub fn render(&mut self, ctx: &mut CanvasRenderingContext2d) {
+        let corners = self.rect().corners();
+        ctx.set_fill_style(&JsValue::from("rgb(55, 255, 55)"));
+        ctx.begin_path();
+        ctx.move_to(corners[0].x, corners[0].y);
+        for corner in corners[1..].iter() {
+            ctx.line_to(corner.x, corner.y);
+        }
+        ctx.close_path();
+        ctx.fill();
+    }
+
+    pub fn point_inside(&self, x: f64, y: f64) -> bool {
+        let rect = self.rect();
+        geometry::point_in_rect(&rect.to_local(&Point::new(x, y)), &rect.local_rect())
+    }
+
+    pub fn circle_inside(&self, circle: &Circle<f64>) -> bool {
+        geometry::circle_intersects_oriented_rect(circle, &self.rect())
+    }
+
+    // Test the capsule swept by a circle of `radius` moving from start to end.
+    // The start is taken relative to the bar as it was, so a spinning bar
+    // sweeps through the player even if the player stays still.
+    pub fn swept_hit(&self, start: &Point<f64>, end: &Point<f64>, radius: f64) -> bool {
+        let rect = self.rect();
+        let turned = self.angle - self.prev_angle;
+        if turned.abs() >= 2.0 * PI {
+            // Right round in one step, so anywhere it can reach
+            let reach = (self.length / 2.0).hypot(self.thickness / 2.0);
+            return geometry::capsule_intersects_circle(start, end, radius, &Circle::new(self.pivot.x, self.pivot.y, reach));
+        }
+        // Relative to the bar the player moves round an arc, so follow it in
+        // short straight pieces rather than cutting straight across
+        let pieces = (turned.abs() / MAX_TURN).ceil().max(1.0) as usize;
+        (0..pieces).any(|i| {
+            let (from, to) = (i as f64 / pieces as f64, (i + 1) as f64 / pieces as f64);
+            let at = |t: f64| Point::new(start.x + (end.x - start.x) * t, start.y + (end.y - start.y) * t);
+            let before = OrientedRect { angle: self.prev_angle + turned * from, ..rect };
+            let after = OrientedRect { angle: self.prev_angle + turned * to, ..rect };
+            geometry::capsule_intersects_rect(&before.to_local(&at(from)), &after.to_local(&at(to)), radius, &rect.local_rect())
+        })
+    }
+}
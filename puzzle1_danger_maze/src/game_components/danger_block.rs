@@ -1,34 +1,91 @@
 use wasm_bindgen::JsValue;
 use web_sys::CanvasRenderingContext2d;
 
-use crate::utils::Rect;
+use crate::geometry;
+use crate::levels::level_model::LevelBlockModel;
+use crate::motion::Animated;
+use crate::utils::{Circle, Point, Rect};
 
 pub struct DangerBlock {
+    // Where it was placed, moving blocks are drawn and hit at rect()
     pub pos: Rect<f64>,
+    // Path and pulse, measured from the centre
+    pub anim: Animated
 }
 
 impl DangerBlock {
+    // Older levels have blocks dragged up or left with a negative size
     pub fn new(x: f64, y: f64, w: f64, h: f64) -> Self {
         DangerBlock {
-            pos: Rect::new(x, y, w, h)
+            pos: geometry::normalise_rect(&Rect::new(x, y, w, h)),
+            anim: Animated::new(None, None)
         }
     }
 
-    pub fn update(&mut self, _delta: f64) {
+    pub fn from_model(model: &LevelBlockModel) -> Self {
+        let mut block = DangerBlock::new(model.x, model.y, model.w, model.h);
+        block.anim = Animated::new(model.motion.clone(), model.pulse);
+        block
+    }
+
+    pub fn restart(&mut self) {
+        self.anim.restart(self.centre());
+    }
+
+    pub fn model(&self) -> LevelBlockModel {
+        let mut model = LevelBlockModel::new(self.pos.loc.x, self.pos.loc.y, self.pos.width, self.pos.height);
+        model.motion = self.anim.motion.clone();
+        model.pulse = self.anim.pulse;
+        model
+    }
 
+    pub fn centre(&self) -> Point<f64> {
+        Point::new(self.pos.loc.x + self.pos.width / 2.0, self.pos.loc.y + self.pos.height / 2.0)
+    }
+
+    // Where the block is now, and how big. Pulsing blocks grow from the middle.
+    pub fn rect(&self) -> Rect<f64> {
+        let inset_x = self.pos.width * (1.0 - self.anim.scale) / 2.0;
+        let inset_y = self.pos.height * (1.0 - self.anim.scale) / 2.0;
+        Rect::new(
+            self.pos.loc.x + self.anim.offset.x + inset_x,
+            self.pos.loc.y + self.anim.offset.y + inset_y,
+            self.pos.width - inset_x * 2.0,
+            self.pos.height - inset_y * 2.0
+        )
+    }
+
+    pub fn is_on(&self) -> bool {
+        self.anim.is_on()
+    }
+
+    pub fn update(&mut self, delta: f64) {
+        self.anim.update(self.centre(), delta);
     }
 
     pub fn render(&mut self, ctx: &mut CanvasRenderingContext2d) {
-        ctx.set_fill_style(&JsValue::from("rgb(55, 255, 55)"));
-        ctx.fill_rect(self.pos.loc.x, self.pos.loc.y, self.pos.width, self.pos.height);
+        if self.anim.scale < 1.0 {
+            // Faded outline at full size so the player can see where it will be
+            ctx.set_stroke_style(&JsValue::from("rgba(55, 255, 55, 0.4)"));
+            ctx.stroke_rect(self.pos.loc.x + self.anim.offset.x, self.pos.loc.y + self.anim.offset.y, self.pos.width, self.pos.height);
+        }
+        if self.is_on() {
+            let rect = self.rect();
+            ctx.set_fill_style(&JsValue::from("rgb(55, 255, 55)"));
+            ctx.fill_rect(rect.loc.x, rect.loc.y, rect.width, rect.height);
+        }
     }
 
     pub fn point_inside(&self, x: f64, y: f64) -> bool {
-        if x > self.pos.loc.x && x < self.pos.loc.x + self.pos.width
-            && y > self.pos.loc.y && y < self.pos.loc.y + self.pos.height {
-                true
-        } else {
-            false
-        }
+        self.is_on() && geometry::point_in_rect(&Point::new(x, y), &self.rect())
     }
-}
\ No newline at end of file
+
+    pub fn circle_inside(&self, circle: &Circle<f64>) -> bool {
+        self.is_on() && geometry::circle_intersects_rect(circle, &self.rect())
+    }
+
+    // Test the capsule swept by a circle of `radius` moving from start to end
+    pub fn swept_hit(&self, start: &Point<f64>, end: &Point<f64>, radius: f64) -> bool {
+        self.is_on() && geometry::capsule_intersects_rect(&self.anim.sweep_start(start), end, radius, &self.rect())
+    }
+}
@@ -1,44 +1,86 @@
 use wasm_bindgen::JsValue;
 use web_sys::CanvasRenderingContext2d;
 
-use crate::utils::Circle;
+use crate::geometry;
+use crate::levels::level_model::LevelCircleModel;
+use crate::motion::Animated;
+use crate::utils::{Circle, Point};
 
 pub struct DangerCircle {
+    // Where it was placed, moving circles are drawn and hit at circle()
     pub pos: Circle<f64>,
+    // Path and pulse, measured from the centre
+    pub anim: Animated
 }
 
 impl DangerCircle {
     pub fn new(x: f64, y: f64, r: f64) -> Self {
         DangerCircle {
-            pos: Circle::new(x, y, r)
+            pos: Circle::new(x, y, r),
+            anim: Animated::new(None, None)
         }
     }
 
-    pub fn update(&mut self, _delta: f64) {
+    pub fn from_model(model: &LevelCircleModel) -> Self {
+        let mut circle = DangerCircle::new(model.x, model.y, model.r);
+        circle.anim = Animated::new(model.motion.clone(), model.pulse);
+        circle
+    }
+
+    pub fn restart(&mut self) {
+        self.anim.restart(self.pos.loc);
+    }
+
+    pub fn model(&self) -> LevelCircleModel {
+        let mut model = LevelCircleModel::new(self.pos.loc.x, self.pos.loc.y, self.pos.size);
+        model.motion = self.anim.motion.clone();
+        model.pulse = self.anim.pulse;
+        model
+    }
+
+    // Where the circle is now, and how big
+    pub fn circle(&self) -> Circle<f64> {
+        Circle::new(self.pos.loc.x + self.anim.offset.x, self.pos.loc.y + self.anim.offset.y, self.pos.size * self.anim.scale)
+    }
 
+    pub fn is_on(&self) -> bool {
+        self.anim.is_on()
+    }
+
+    pub fn update(&mut self, delta: f64) {
+        self.anim.update(self.pos.loc, delta);
     }
 
     pub fn render(&mut self, ctx: &mut CanvasRenderingContext2d) {
+        let circle = self.circle();
+        if self.anim.scale < 1.0 {
+            // Faded outline at full size so the player can see where it will be
+            ctx.set_stroke_style(&JsValue::from("rgba(55, 255, 55, 0.4)"));
+            ctx.begin_path();
+            let _ = ctx.arc(circle.loc.x, circle.loc.y, self.pos.size, 0.0, std::f64::consts::PI * 2.0);
+            ctx.stroke();
+        }
+        if !self.is_on() {
+            return;
+        }
         ctx.set_fill_style(&JsValue::from("rgb(55, 255, 55)"));
         let _ = ctx.begin_path();
         let _ = ctx.arc(
-                    self.pos.loc.x,
-                    self.pos.loc.y,
-                    self.pos.size, 
+                    circle.loc.x,
+                    circle.loc.y,
+                    circle.size, 
                     0.0, 
                     std::f64::consts::PI * 2.0
                 );
         let _ = ctx.fill();
     }
 
-    pub fn point_inside(&self, x: f64, y: f64, radius: f64) -> bool {
-        let diff_x = self.pos.loc.x - x;
-        let diff_y = self.pos.loc.y - y;
-        let dist = ((diff_x*diff_x) + (diff_y*diff_y)).sqrt();
-        if dist < self.pos.size + radius {
-            true
-        } else {
-            false
-        }
+    pub fn circle_inside(&self, circle: &Circle<f64>) -> bool {
+        self.is_on() && geometry::circle_intersects_circle(&self.circle(), circle)
     }
-}
\ No newline at end of file
+
+    // Test the capsule swept by a circle of `radius` moving from start to end
+    pub fn swept_hit(&self, start: &Point<f64>, end: &Point<f64>, radius: f64) -> bool {
+        self.is_on() && geometry::capsule_intersects_circle(&self.anim.sweep_start(start), end, radius, &self.circle())
+    }
+}
@@ -0,0 +1,66 @@
+use std::vec::Vec;
+
+use wasm_bindgen::JsValue;
+use web_sys::CanvasRenderingContext2d;
+
+use crate::geometry;
+use crate::levels::level_model::{LevelPointModel, LevelPolygonModel};
+use crate::utils::{Circle, Point};
+
+pub struct DangerPolygon {
+    // Corners in order, the last joins back to the first
+    pub points: Vec::<Point<f64>>
+}
+
+impl DangerPolygon {
+    pub fn new(points: Vec::<Point<f64>>) -> Self {
+        DangerPolygon {
+            points
+        }
+    }
+
+    pub fn from_model(model: &LevelPolygonModel) -> Self {
+        DangerPolygon::new(model.points.iter().map(|p| Point::new(p.x, p.y)).collect())
+    }
+
+    pub fn model(&self) -> LevelPolygonModel {
+        LevelPolygonModel {
+            points: self.points.iter().map(|p| LevelPointModel { x: p.x, y: p.y }).collect()
+        }
+    }
+
+    // Moves every corner by the same amount
+    pub fn translate(&mut self, x: f64, y: f64) {
+        for point in self.points.iter_mut() {
+            point.x += x;
+            point.y += y;
+        }
+    }
+
+    pub fn render(&mut self, ctx: &mut CanvasRenderingContext2d) {
+        if self.points.len() < 3 {
+            return;
+        }
+        ctx.set_fill_style(&JsValue::from("rgb(55, 255, 55)"));
+        ctx.begin_path();
+        ctx.move_to(self.points[0].x, self.points[0].y);
+        for point in self.points[1..].iter() {
+            ctx.line_to(point.x, point.y);
+        }
+        ctx.close_path();
+        ctx.fill();
+    }
+
+    pub fn point_inside(&self, x: f64, y: f64) -> bool {
+        geometry::point_in_polygon(&Point::new(x, y), &self.points)
+    }
+
+    pub fn circle_inside(&self, circle: &Circle<f64>) -> bool {
+        geometry::circle_intersects_polygon(circle, &self.points)
+    }
+
+    // Test the capsule swept by a circle of `radius` moving from start to end
+    pub fn swept_hit(&self, start: &Point<f64>, end: &Point<f64>, radius: f64) -> bool {
+        geometry::capsule_intersects_polygon(start, end, radius, &self.points)
+    }
+}
@@ -1,11 +1,15 @@
 use web_sys::{CanvasRenderingContext2d, HtmlImageElement};
 
-use crate::utils::Point;
+use crate::geometry;
+use crate::utils::{Point, Rect};
+
+// Used for hit testing before the image has finished loading
+const MIN_IMAGE_SIZE: f64 = 40.0;
 
 pub struct Image {
     filename: String,
     image: HtmlImageElement,
-    loc: Point<f64>
+    pub loc: Point<f64>
 }
 
 impl Image {
@@ -21,6 +25,21 @@ impl Image {
         }
     }
 
+    pub fn filename(&self) -> &str {
+        &self.filename
+    }
+
+    // Images are drawn centred on their location
+    pub fn bounds(&self) -> Rect<f64> {
+        let w = (self.image.width() as f64).max(MIN_IMAGE_SIZE);
+        let h = (self.image.height() as f64).max(MIN_IMAGE_SIZE);
+        Rect::new(self.loc.x - w / 2.0, self.loc.y - h / 2.0, w, h)
+    }
+
+    pub fn point_inside(&self, x: f64, y: f64) -> bool {
+        geometry::point_in_rect(&Point::new(x, y), &self.bounds())
+    }
+
     pub fn render(&mut self, ctx: &mut CanvasRenderingContext2d) {
         let _ = ctx.translate(self.loc.x, self.loc.y);
         let _ = ctx.draw_image_with_html_image_element_and_sw_and_sh_and_dx_and_dy_and_dw_and_dh(
@@ -1,7 +1,10 @@
 pub mod confetti;
 pub mod danger_block;
+pub mod danger_bar;
 pub mod danger_circle;
+pub mod danger_polygon;
 pub mod goal;
 pub mod image;
 pub mod player;
-pub mod mouse_handler;
\ No newline at end of file
+pub mod mouse_handler;
+pub mod wall;
\ No newline at end of file
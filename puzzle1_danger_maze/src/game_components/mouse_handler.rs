@@ -13,6 +13,12 @@ pub struct MouseHandler {
 const CLICK_INDICATOR_GROWTH_SPEED: f64 = 0.06;
 const CLICK_INDICATOR_MAX_SIZE: f64 = 20.0;
 
+impl Default for MouseHandler {
+    fn default() -> Self {
+        MouseHandler::new()
+    }
+}
+
 impl MouseHandler {
     pub fn new() -> Self {
         MouseHandler {
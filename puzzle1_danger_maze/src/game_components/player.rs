@@ -11,12 +11,10 @@ pub struct Player {
     indicator: Vec::<Circle<f64>>
 }
 
-const PLAYER_SIZE: f64 = 20.0;
+pub const PLAYER_SIZE: f64 = 20.0;
 const PLAYER_INDICATOR_GROWTH_SPEED: f64 = 0.06;
 const PLAYER_INDICATOR_MAX_SIZE: f64 = 30.0;
 
-const PLAYER_SAMPLE_NUM: f64 = 8.0;
-
 impl Player {
     pub fn new(x: f64, y: f64) -> Self {
         Player {
@@ -85,20 +83,8 @@ impl Player {
         self.is_moving = true;
     }
 
-    pub fn get_sample_points(&self) -> Vec::<Point<f64>> {
-        let mut ret = Vec::new();
-        let step_size = std::f64::consts::PI * 2.0 / PLAYER_SAMPLE_NUM;
-        let mut cur = 0.0;
-        while cur < std::f64::consts::PI * 2.0 {
-            ret.push(
-                Point::new(
-                    self.loc.x + (cur.sin() * self.player_size()),
-                    self.loc.y + (cur.cos() * self.player_size())
-                )
-            );
-            cur += step_size;
-        }
-        ret
+    pub fn get_circle(&self) -> Circle<f64> {
+        Circle::new(self.loc.x, self.loc.y, PLAYER_SIZE)
     }
 
     pub fn reset(&mut self) {
@@ -106,9 +92,4 @@ impl Player {
         self.loc.y = self.start.y;
         self.is_moving = false;
     }
-
-    pub fn set_start(&mut self, x: f64, y: f64) {
-        self.start = Point::new(x,y);
-        self.loc = Point::new(x,y);
-    }
 }
\ No newline at end of file
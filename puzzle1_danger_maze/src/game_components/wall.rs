@@ -0,0 +1,42 @@
+use wasm_bindgen::JsValue;
+use web_sys::CanvasRenderingContext2d;
+
+use crate::geometry;
+use crate::levels::level_model::LevelWallModel;
+use crate::utils::{Circle, Point, Rect};
+
+// Safe to touch, the player slides along it instead of being reset
+pub struct Wall {
+    pub pos: Rect<f64>
+}
+
+impl Wall {
+    pub fn new(x: f64, y: f64, w: f64, h: f64) -> Self {
+        Wall {
+            pos: geometry::normalise_rect(&Rect::new(x, y, w, h))
+        }
+    }
+
+    pub fn from_model(model: &LevelWallModel) -> Self {
+        Wall::new(model.x, model.y, model.w, model.h)
+    }
+
+    pub fn model(&self) -> LevelWallModel {
+        LevelWallModel::new(self.pos.loc.x, self.pos.loc.y, self.pos.width, self.pos.height)
+    }
+
+    pub fn render(&mut self, ctx: &mut CanvasRenderingContext2d) {
+        ctx.set_fill_style(&JsValue::from("rgb(150, 150, 170)"));
+        ctx.fill_rect(self.pos.loc.x, self.pos.loc.y, self.pos.width, self.pos.height);
+        ctx.set_stroke_style(&JsValue::from("rgb(210, 210, 230)"));
+        ctx.stroke_rect(self.pos.loc.x, self.pos.loc.y, self.pos.width, self.pos.height);
+    }
+
+    pub fn point_inside(&self, x: f64, y: f64) -> bool {
+        geometry::point_in_rect(&Point::new(x, y), &self.pos)
+    }
+
+    pub fn circle_inside(&self, circle: &Circle<f64>) -> bool {
+        geometry::circle_intersects_rect(circle, &self.pos)
+    }
+}
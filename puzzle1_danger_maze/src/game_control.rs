@@ -4,28 +4,40 @@ use wasm_bindgen::{prelude::*, JsCast};
 use web_sys::window;
 use js_sys::Date;
 use gloo_console::log;
-use gloo_net::http::Request;
 
-use crate::game_components::danger_circle::DangerCircle;
 use crate::game_components::image::Image;
-use crate::game_components::{danger_block::DangerBlock, goal::Goal, mouse_handler::MouseHandler, player::Player};
+use crate::game_components::mouse_handler::MouseHandler;
+use crate::game_state::GameState;
+use crate::geometry;
+use crate::levels::level_loader::{self, LevelLoadError};
 use crate::levels::level_model::*;
-use crate::utils;
+use crate::level_select::LevelSelect;
+use crate::levels::manifest::{self, LevelManifest, DEFAULT_MANIFEST};
+use crate::results::{LevelResult, RunResults};
+use crate::progress::{LocalStorageProgress, Progress, ProgressStore, PROGRESS_KEY};
+use crate::simulation::{PointerInput, SimEvent, Simulation, SIM_MAX_CATCH_UP, SIM_STEP};
+use crate::utils::{self, Point, Rect};
 
 pub struct GameControl {
-    state: String,
+    state: GameState,
+    state_time: f64,
+    paused_from: GameState,
     pub mouse: MouseHandler,
-    pub player: Player,
-    cur_level: i32,
-    blocks: Vec::<DangerBlock>,
-    circles: Vec::<DangerCircle>,
+    sim: Simulation,
+    manifest: Option<LevelManifest>,
+    level_select: Option<LevelSelect>,
+    menu_requested: bool,
+    cur_level: usize,
+    progress: Progress,
+    progress_store: Box<dyn ProgressStore>,
+    results: RunResults,
+    load_error: Option<LevelLoadError>,
     images: Vec::<Image>,
-    goal: Goal,
     canvas: NodeRef,
     callback: Closure<dyn FnMut()>,
     last_update: f64,
-    cur_time: f64,
-    is_loading: bool,
+    step_time: f64,
+    frame_pending: bool,
 }
 
 pub enum GameMsg {
@@ -37,18 +49,40 @@ pub enum GameMsg {
     TouchMove((f64, f64)),
     KeyDown(String),
     KeyUp(String),
-    LoadLevel(i32),
+    LoadManifest,
+    ManifestLoaded(LevelManifest),
+    LoadLevel(usize),
+    ShowMenu,
+    ThumbnailLoaded((usize, LevelModel)),
     LevelLoad(LevelModel),
+    LevelLoadFailed(LevelLoadError),
+    PlayerDied,
+    DeathOver,
+    LevelWon,
+    TogglePause,
     Render,
     Null
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Properties)]
-pub struct GameControlProps;
+pub struct GameControlProps {
+    // Level id or number to jump straight to
+    #[prop_or_default]
+    pub level: Option<String>,
+    // Open on the level select grid
+    #[prop_or_default]
+    pub show_menu: bool,
+}
 
 pub const GAME_HEIGHT: f64 = 800.0;
 pub const GAME_WIDTH: f64 = 1280.0;
-pub const START_LEVEL: i32 = 1;
+// Index into the level manifest
+pub const START_LEVEL: usize = 0;
+// How long the reset animation plays for, in ms
+pub const DEAD_TIME: f64 = 500.0;
+// Buttons on the load error screen
+const RETRY_BUTTON: (f64, f64, f64, f64) = (340.0, 460.0, 280.0, 90.0);
+const MENU_BUTTON: (f64, f64, f64, f64) = (660.0, 460.0, 280.0, 90.0);
 
 impl Component for GameControl {
     type Message = GameMsg;
@@ -62,22 +96,31 @@ impl Component for GameControl {
         ctx.link().send_message(GameMsg::Render);
 
         let comp_ctx = ctx.link().clone();
-        comp_ctx.send_message(GameMsg::LoadLevel(START_LEVEL));
+        comp_ctx.send_message(GameMsg::LoadManifest);
+
+        let progress_store = LocalStorageProgress::new(PROGRESS_KEY);
+        let progress = progress_store.load();
 
         GameControl{
-            state: "PLAY".to_string(),
+            state: GameState::Loading,
+            state_time: 0.0,
+            paused_from: GameState::Ready,
             mouse: MouseHandler::new(),
-            player: Player::new(100.0, 100.0),
+            sim: Simulation::new(&LevelModel::new()),
+            manifest: None,
+            level_select: None,
+            menu_requested: ctx.props().show_menu,
             cur_level: START_LEVEL,
-            blocks: Vec::<DangerBlock>::new(),
-            circles: Vec::<DangerCircle>::new(),
+            progress,
+            progress_store: Box::new(progress_store),
+            results: RunResults::new(),
+            load_error: None,
             images: Vec::<Image>::new(),
-            goal: Goal::new(1100.0, 400.0),
             canvas: NodeRef::default(),
             callback: callback,
             last_update: Date::now(),
-            cur_time: 0.0,
-            is_loading: true,
+            step_time: 0.0,
+            frame_pending: false,
         }
     }
 
@@ -85,95 +128,141 @@ impl Component for GameControl {
         match msg {
             GameMsg::MouseDown(evt) => {
                 self.mouse.mouse_down = true;
-                let dist = self.player.dist_from_player(evt.0, evt.1);
-                if dist < self.player.player_size() {
-                    self.player.set_moving();
-                    // self.player.is_moving = true;
-                } else {
+                if !self.pointer_down(ctx, evt.0, evt.1) {
                     self.mouse.click(evt.0, evt.1);
                 }
-                
-                if self.state == "WIN" {
-                    self.state = "PLAY".to_string();
-                    let comp_ctx = ctx.link().clone();
-                    comp_ctx.send_message(GameMsg::LoadLevel(self.cur_level + 1));
-                }
-
                 true
             },
-            GameMsg::MouseUp(_evt) => {
+            GameMsg::MouseUp(evt) => {
                 self.mouse.mouse_down = false;
-                self.player.is_moving = false;
+                self.sim.handle_input(PointerInput::Up(evt.0, evt.1));
                 true
             },
             GameMsg::MouseMove(evt) => {
                 self.mouse.update_pos(evt.0, evt.1);
+                self.sim.handle_input(PointerInput::Move(evt.0, evt.1));
                 // log!("Event here => ", self.mousehandler.offset_x, self.mousehandler.offset_y);
                 true
             },
             GameMsg::TouchStart(evt) => {
                 // log!("Event here TouchStart => ", evt.0, evt.1);
                 self.mouse.mouse_down = true;
-                let dist = self.player.dist_from_player(evt.0, evt.1);
-                if dist < self.player.player_size() {
-                    self.player.is_moving = true;
-                }
+                self.pointer_down(ctx, evt.0, evt.1);
                 true
             },
-            GameMsg::TouchEnd(_evt) => {
+            GameMsg::TouchEnd(evt) => {
                 // log!("Event here TouchEnd => ", evt.0, evt.1);
                 self.mouse.mouse_down = false;
-                self.player.is_moving = false;
+                self.sim.handle_input(PointerInput::Up(evt.0, evt.1));
                 true
             },
             GameMsg::TouchMove(evt) => {
                 self.mouse.update_pos(evt.0, evt.1);
+                self.sim.handle_input(PointerInput::Move(evt.0, evt.1));
                 // log!("Event here TouchMove => ", evt.0, evt.1);
                 true
             },
-            GameMsg::KeyDown(_key) => {
+            GameMsg::KeyDown(key) => {
+                if key == "KeyP" || key == "Escape" {
+                    ctx.link().send_message(GameMsg::TogglePause);
+                } else if key == "KeyM" {
+                    ctx.link().send_message(GameMsg::ShowMenu);
+                }
                 true
             },
             GameMsg::KeyUp(_key) => {
                 true
             },
-            GameMsg::LoadLevel(level_num) => {
-                self.is_loading = true;
+            GameMsg::LoadManifest => {
+                self.set_state(GameState::Loading);
                 let comp_ctx = ctx.link().clone();
-                self.cur_level = level_num;
                 wasm_bindgen_futures::spawn_local(async move {
-                    let lvl_str = format!("assets/levels/level{}.json", level_num);
-                    log!(lvl_str.clone());
-                    let fetched_level = Request::get(lvl_str.as_str())
-                                .send()
-                                .await
-                                .unwrap()
-                                .json::<LevelModel>()
-                                .await
-                                .unwrap();
-                    
-                    comp_ctx.send_message(GameMsg::LevelLoad(fetched_level));
+                    match manifest::fetch_manifest(DEFAULT_MANIFEST).await {
+                        Ok(manifest) => comp_ctx.send_message(GameMsg::ManifestLoaded(manifest)),
+                        Err(err) => comp_ctx.send_message(GameMsg::LevelLoadFailed(err))
+                    }
                 });
                 false
             },
-            GameMsg::LevelLoad(level_model) => {
-                self.player.is_moving = false;
-                self.player.set_start(level_model.player.x, level_model.player.y);
-        
-                self.goal.circle.loc.x = level_model.goal.x;
-                self.goal.circle.loc.y = level_model.goal.y;
-                
-                let mut blocks = Vec::<DangerBlock>::new();
-                for b in level_model.danger_blocks.iter() {
-                    blocks.push(DangerBlock::new(b.x, b.y, b.w, b.h));
+            GameMsg::ManifestLoaded(manifest) => {
+                // Go to the linked level, or pick up where the player left off last time.
+                // A link to a level that isn't in the pack goes to the menu instead.
+                let start = match ctx.props().level.as_ref() {
+                    Some(level) => manifest.find(level),
+                    None => Some(self.progress.resume_index(&manifest))
+                };
+                self.level_select = Some(LevelSelect::new(&manifest));
+                self.manifest = Some(manifest);
+                self.results.clear();
+                match start {
+                    Some(start) if !self.menu_requested => ctx.link().send_message(GameMsg::LoadLevel(start)),
+                    _ => ctx.link().send_message(GameMsg::ShowMenu)
+                }
+                false
+            },
+            GameMsg::ShowMenu => {
+                if self.manifest.is_none() {
+                    // Come back here once the manifest is in
+                    self.menu_requested = true;
+                    ctx.link().send_message(GameMsg::LoadManifest);
+                    return false;
+                }
+                if !self.state.can_move_to(GameState::Menu) {
+                    return false;
                 }
-                self.blocks = blocks;
+                self.menu_requested = false;
+                self.set_state(GameState::Menu);
+                self.load_thumbnails(ctx);
+                self.restart_frame_loop(ctx);
+                true
+            },
+            GameMsg::ThumbnailLoaded((index, level_model)) => {
+                if let Some(level_select) = self.level_select.as_mut() {
+                    level_select.set_thumbnail(index, level_model);
+                }
+                true
+            },
+            GameMsg::LoadLevel(level_num) => {
+                let entry = match &self.manifest {
+                    Some(manifest) => manifest.entry(level_num).cloned(),
+                    None => {
+                        ctx.link().send_message(GameMsg::LoadManifest);
+                        return false;
+                    }
+                };
+                self.set_state(GameState::Loading);
+                self.cur_level = level_num;
 
-                let mut circles = Vec::<DangerCircle>::new();
-                for c in level_model.danger_circles.iter() {
-                    circles.push(DangerCircle::new(c.x,c.y,c.r));
+                let entry = match entry {
+                    Some(entry) => entry,
+                    None => {
+                        // Walked off the end of the manifest, so the run is over
+                        self.sim = Simulation::new(&Self::end_level());
+                        self.images = Vec::new();
+                        self.set_state(GameState::Finished);
+                        self.restart_frame_loop(ctx);
+                        return true;
+                    }
+                };
+                if !self.is_level_unlocked(level_num) {
+                    log!(format!("Level {} is locked", entry.id));
+                    ctx.link().send_message(GameMsg::ShowMenu);
+                    return false;
                 }
-                self.circles = circles;
+
+                let comp_ctx = ctx.link().clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    let lvl_str = entry.file;
+                    log!(lvl_str.clone());
+                    match level_loader::fetch_level(lvl_str.as_str()).await {
+                        Ok(fetched_level) => comp_ctx.send_message(GameMsg::LevelLoad(fetched_level)),
+                        Err(err) => comp_ctx.send_message(GameMsg::LevelLoadFailed(err))
+                    }
+                });
+                false
+            },
+            GameMsg::LevelLoad(level_model) => {
+                self.sim = Simulation::new(&level_model);
 
                 let mut images = Vec::<Image>::new();
                 for i in level_model.images.iter() {
@@ -181,15 +270,77 @@ impl Component for GameControl {
                 }
                 self.images = images;
 
-                let comp_ctx = ctx.link().clone();
-                comp_ctx.send_message(GameMsg::Render);
+                self.restart_frame_loop(ctx);
 
-                self.cur_time = 0.0;
-                self.is_loading = false;
+                self.step_time = 0.0;
+                self.load_error = None;
+                if self.is_credits_level() {
+                    self.set_state(GameState::Finished);
+                } else {
+                    self.set_state(GameState::Ready);
+                }
+                true
+            },
+            GameMsg::LevelLoadFailed(err) => {
+                log!(err.to_string());
+                self.load_error = Some(err);
+                self.set_state(GameState::Error);
+                self.restart_frame_loop(ctx);
+                true
+            },
+            GameMsg::PlayerDied => {
+                // Only count deaths the state machine lets through, not repeats while already dead
+                if !self.state.can_move_to(GameState::Dead) {
+                    return false;
+                }
+                self.progress.record_death();
+                self.save_progress();
+                self.set_state(GameState::Dead);
+                true
+            },
+            GameMsg::DeathOver => {
+                self.set_state(GameState::Playing);
+                true
+            },
+            GameMsg::LevelWon => {
+                if let Some(id) = self.level_id(self.cur_level) {
+                    let previous_best = self.progress.best_time(&id);
+                    let new_best = self.progress.record_win(&id, self.sim.elapsed());
+                    self.results.push(LevelResult {
+                        title: self.level_title(),
+                        time: self.sim.elapsed(),
+                        resets: self.sim.resets(),
+                        previous_best,
+                        new_best
+                    });
+                }
+                // Unlock the next level now in case they leave on the win screen
+                if let Some(next_id) = self.level_id(self.cur_level + 1) {
+                    self.progress.unlock(&next_id);
+                }
+                self.save_progress();
+                self.set_state(GameState::Won);
+                true
+            },
+            GameMsg::TogglePause => {
+                if self.state == GameState::Paused {
+                    let resume = self.paused_from;
+                    self.set_state(resume);
+                } else if self.state.can_pause() {
+                    // Drop the player so it doesn't jump to the pointer on resume
+                    let loc = self.mouse.loc;
+                    self.sim.handle_input(PointerInput::Up(loc.x, loc.y));
+                    self.paused_from = if self.sim.has_started() {
+                        GameState::Playing
+                    } else {
+                        GameState::Ready
+                    };
+                    self.set_state(GameState::Paused);
+                }
                 true
             },
             GameMsg::Render => {
-                self.render();
+                self.render(ctx);
                 true
             },
             GameMsg::Null => {
@@ -254,77 +405,211 @@ impl Component for GameControl {
 }
 
 impl GameControl {
-    fn game_update(&mut self) {
-        let cur_time = Date::now();
-        let diff = cur_time - self.last_update;
-        
-        if self.state != "WIN" {
-            self.cur_time += diff;
+    fn set_state(&mut self, next: GameState) {
+        if self.state == next {
+            return;
         }
+        if !self.state.can_move_to(next) {
+            log!(format!("Ignoring state change {:?} -> {:?}", self.state, next));
+            return;
+        }
+        self.state = next;
+        self.state_time = 0.0;
+    }
 
-        self.last_update = cur_time;
+    // Restart the frame loop unless a frame is already on its way
+    fn restart_frame_loop(&self, ctx: &Context<Self>) {
+        if !self.frame_pending {
+            ctx.link().send_message(GameMsg::Render);
+        }
+    }
+
+    fn button_rect(button: (f64, f64, f64, f64)) -> Rect<f64> {
+        Rect::new(button.0, button.1, button.2, button.3)
+    }
 
-        if self.player.is_moving {
-            self.player.loc.x = self.mouse.loc.x;
-            self.player.loc.y = self.mouse.loc.y;
+    // Returns true if the press picked up the player
+    fn pointer_down(&mut self, ctx: &Context<Self>, x: f64, y: f64) -> bool {
+        if self.state.accepts_input() {
+            let grabbed = self.sim.handle_input(PointerInput::Down(x, y));
+            if grabbed {
+                self.set_state(GameState::Playing);
+            }
+            return grabbed;
         }
 
-        self.goal.update(diff, self.state == "WIN");
-        self.player.update(diff);
-        self.mouse.update(diff);
-        // Check collision with every block
-        for block in self.blocks.iter_mut() {
-            block.update(diff);
-            
-            let points = self.player.get_sample_points();
-            for pt in points.iter() {
-                if block.point_inside(pt.x, pt.y) {
-                    self.player.reset();
+        match self.state {
+            GameState::Won => {
+                ctx.link().send_message(GameMsg::LoadLevel(self.cur_level + 1));
+            },
+            GameState::Finished => {
+                self.results.clear();
+                ctx.link().send_message(GameMsg::LoadLevel(START_LEVEL));
+            },
+            GameState::Menu => {
+                let level_select = match self.level_select.as_mut() {
+                    Some(level_select) => level_select,
+                    None => return false
+                };
+                if level_select.turn_page_at(x, y) {
+                    return false;
                 }
-            }
+                if let Some(index) = level_select.level_at(x, y, &self.progress) {
+                    self.results.clear();
+                    ctx.link().send_message(GameMsg::LoadLevel(index));
+                }
+            },
+            GameState::Error => {
+                let pt = Point::new(x, y);
+                if geometry::point_in_rect(&pt, &Self::button_rect(RETRY_BUTTON)) {
+                    ctx.link().send_message(GameMsg::LoadLevel(self.cur_level));
+                } else if geometry::point_in_rect(&pt, &Self::button_rect(MENU_BUTTON)) {
+                    ctx.link().send_message(GameMsg::ShowMenu);
+                }
+            },
+            _ => {}
         }
-        // Check collision with circles
-        for circle in self.circles.iter_mut() {
-            circle.update(diff);
-            if circle.point_inside(self.player.loc.x, self.player.loc.y, self.player.player_size()) {
-                self.player.reset();
+        false
+    }
+
+    // Fetch every level once so the menu can draw a preview of it
+    fn load_thumbnails(&self, ctx: &Context<Self>) {
+        let level_select = match &self.level_select {
+            Some(level_select) => level_select,
+            None => return
+        };
+        for (index, entry) in level_select.entries().iter().enumerate() {
+            if level_select.has_thumbnail(index) {
+                continue;
             }
+            let comp_ctx = ctx.link().clone();
+            let path = entry.file.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                // A missing preview isn't worth an error screen
+                if let Ok(level_model) = level_loader::fetch_level(path.as_str()).await {
+                    comp_ctx.send_message(GameMsg::ThumbnailLoaded((index, level_model)));
+                }
+            });
         }
-        // Check bofunds for player
-        if self.player.loc.x < self.player.player_size() || self.player.loc.x > GAME_WIDTH - self.player.player_size() {
-            self.player.reset();
-        }
-        if self.player.loc.y < self.player.player_size() || self.player.loc.y > GAME_HEIGHT - self.player.player_size() {
-            self.player.reset();
-        }
+    }
+
+    fn is_credits_level(&self) -> bool {
+        self.manifest.as_ref()
+            .map(|manifest| manifest.is_credits(self.cur_level))
+            .unwrap_or(false)
+    }
+
+    // Levels are opened by winning the one before. The credits only ever follow a win on the last level.
+    fn is_level_unlocked(&self, index: usize) -> bool {
+        let is_credits = self.manifest.as_ref()
+            .map(|manifest| manifest.is_credits(index))
+            .unwrap_or(false);
+        is_credits || self.level_select.as_ref()
+            .map(|level_select| level_select.is_unlocked(index, &self.progress))
+            .unwrap_or(false)
+    }
 
-        let win_dist = self.goal.get_dist() + self.player.player_size();
-        if self.player.dist_from_player(self.goal.circle.loc.x, self.goal.circle.loc.y) < win_dist {
-            self.state = "WIN".to_string();
-        } 
+    // Id of a playable level, the credits don't count
+    fn level_id(&self, index: usize) -> Option<String> {
+        self.manifest.as_ref()
+            .and_then(|manifest| manifest.levels.get(index))
+            .map(|entry| entry.id.clone())
+    }
 
+    fn save_progress(&mut self) {
+        self.progress_store.save(&self.progress);
     }
 
-    fn render(&mut self) {
-        if self.is_loading {
+    fn level_title(&self) -> String {
+        self.manifest.as_ref()
+            .and_then(|manifest| manifest.entry(self.cur_level))
+            .map(|entry| entry.title.clone())
+            .unwrap_or_default()
+    }
+
+    // Empty scene shown when the pack has no credits level
+    fn end_level() -> LevelModel {
+        let mut level = LevelModel::new();
+        level.goal.x = GAME_WIDTH * 2.0;
+        level.goal.y = GAME_HEIGHT * 2.0;
+        level
+    }
+
+    fn game_update(&mut self, ctx: &Context<Self>) {
+        let cur_time = Date::now();
+        let diff = cur_time - self.last_update;
+        self.last_update = cur_time;
+        self.state_time += diff;
+
+        self.mouse.update(diff);
+
+        if self.state == GameState::Dead && self.state_time > DEAD_TIME {
+            ctx.link().send_message(GameMsg::DeathOver);
+        }
+        if !self.state.is_running() {
+            self.step_time = 0.0;
             return;
         }
 
-        self.game_update();
-     
+        // Advance the rules in fixed steps, whatever the frame rate
+        self.step_time = f64::min(self.step_time + diff, SIM_MAX_CATCH_UP);
+        while self.step_time >= SIM_STEP {
+            self.step_time -= SIM_STEP;
+            for event in self.sim.step(SIM_STEP) {
+                match event {
+                    SimEvent::Reset => ctx.link().send_message(GameMsg::PlayerDied),
+                    SimEvent::Won => ctx.link().send_message(GameMsg::LevelWon)
+                }
+            }
+        }
+    }
+
+    fn render(&mut self, ctx: &Context<Self>) {
+        self.frame_pending = false;
+        let canvas: HtmlCanvasElement = match self.canvas.cast() {
+            Some(canvas) => canvas,
+            None => return
+        };
 
-        let canvas: HtmlCanvasElement = self.canvas.cast().unwrap();
-        
         // Make sure the we reset the draw surface to prevent stretching
         canvas.set_width(canvas.client_width() as u32);
         canvas.set_height(canvas.client_height() as u32);
 
-        let mut ctx: CanvasRenderingContext2d =
+        let mut ctx_2d: CanvasRenderingContext2d =
             canvas.get_context("2d").unwrap().unwrap().unchecked_into();
 
-        ctx.set_fill_style(&JsValue::from("rgb(55, 55, 55)"));
-        ctx.fill_rect(0.0, 0.0, GAME_WIDTH, GAME_HEIGHT);
+        ctx_2d.set_fill_style(&JsValue::from("rgb(55, 55, 55)"));
+        ctx_2d.fill_rect(0.0, 0.0, GAME_WIDTH, GAME_HEIGHT);
+
+        if self.state == GameState::Loading {
+            // Stop the frame loop until the level arrives, LevelLoad restarts it
+            self.render_overlay(&mut ctx_2d);
+            return;
+        }
+
+        self.game_update(ctx);
 
+        if self.state != GameState::Error && self.state != GameState::Menu {
+            self.render_level(&mut ctx_2d);
+        }
+
+        match self.state {
+            GameState::Ready | GameState::Playing | GameState::Dead | GameState::Paused => {
+                self.sim.player.render(&mut ctx_2d);
+            },
+            _ => {}
+        }
+        self.render_overlay(&mut ctx_2d);
+        self.mouse.render(&mut ctx_2d);
+
+        window()
+            .unwrap()
+            .request_animation_frame(self.callback.as_ref().unchecked_ref())
+            .unwrap();
+        self.frame_pending = true;
+    }
+
+    fn render_level(&mut self, ctx: &mut CanvasRenderingContext2d) {
         // Game border
         ctx.set_stroke_style(&JsValue::from("rgb(255, 255, 0)"));
         ctx.move_to(0.0, 0.0);
@@ -335,63 +620,123 @@ impl GameControl {
         ctx.stroke();
         
         // Start game render
-        for block in self.blocks.iter_mut() {
-            block.render(&mut ctx);
+        for wall in self.sim.walls.iter_mut() {
+            wall.render(ctx);
+        }
+        for block in self.sim.blocks.iter_mut() {
+            block.render(ctx);
+        }
+        for circle in self.sim.circles.iter_mut() {
+            circle.render(ctx);
         }
-        for circle in self.circles.iter_mut() {
-            circle.render(&mut ctx);
+        for bar in self.sim.bars.iter_mut() {
+            bar.render(ctx);
+        }
+        for polygon in self.sim.polygons.iter_mut() {
+            polygon.render(ctx);
         }
 
         for image in self.images.iter_mut() {
-            image.render(&mut ctx);
+            image.render(ctx);
         }
 
-        self.goal.render(&mut ctx);
+        self.sim.goal.render(ctx);
 
         // Level string
         ctx.set_font("64px arial");
-        let level_str = format!("Level: {}", self.cur_level);
-        utils::drop_shadow_string(&mut ctx, level_str, 1010.0, 750.0);
+        if self.state != GameState::Finished {
+            let level_str = format!("Level: {}", self.cur_level + 1);
+            utils::drop_shadow_string(ctx, level_str, 1010.0, 750.0);
+        }
         
         // Time String
         let time_str = self.get_time_str();
-        utils::drop_shadow_string(&mut ctx, time_str, 20.0, 750.0);
-
-        if self.state == "PLAY" {
-            self.player.render(&mut ctx);
-        } else if self.state == "WIN" {
-            // Win screen prompt
-            ctx.set_fill_style(&JsValue::from("rgb(0,0,0)"));
-            ctx.set_font("128px arial");
-            let load_string = "GOAL";
-            
-            let _ = ctx.fill_text(load_string, 305.0, 355.0);
-            ctx.set_fill_style(&JsValue::from("rgb(255,0,0)"));
-            let _ = ctx.fill_text(load_string, 300.0, 350.0);
-
-            ctx.set_font("64px arial");
-            let load_string = "Click to continue".to_string();
-            utils::drop_shadow_string(&mut ctx, load_string, 300.0, 450.0);
-        }
-        self.mouse.render(&mut ctx);
+        utils::drop_shadow_string(ctx, time_str, 20.0, 750.0);
+    }
 
-        window()
-            .unwrap()
-            .request_animation_frame(self.callback.as_ref().unchecked_ref())
-            .unwrap();
+    fn render_overlay(&self, ctx: &mut CanvasRenderingContext2d) {
+        match self.state {
+            GameState::Loading => {
+                ctx.set_font("64px arial");
+                utils::drop_shadow_string(ctx, "Loading...".to_string(), 500.0, 400.0);
+            },
+            GameState::Ready => {
+                ctx.set_font("32px arial");
+                let ready_str = format!("{} - grab the player to start", self.level_title());
+                utils::drop_shadow_string(ctx, ready_str, 20.0, 50.0);
+            },
+            GameState::Playing => {},
+            GameState::Dead => {
+                // Red flash that fades out as the player is put back
+                let alpha = (1.0 - (self.state_time / DEAD_TIME)).clamp(0.0, 1.0) * 0.5;
+                ctx.set_fill_style(&JsValue::from(format!("rgba(255, 0, 0, {})", alpha)));
+                ctx.fill_rect(0.0, 0.0, GAME_WIDTH, GAME_HEIGHT);
+            },
+            GameState::Won => {
+                // Win screen prompt
+                ctx.set_fill_style(&JsValue::from("rgb(0,0,0)"));
+                ctx.set_font("128px arial");
+                let load_string = "GOAL";
+                
+                let _ = ctx.fill_text(load_string, 305.0, 355.0);
+                ctx.set_fill_style(&JsValue::from("rgb(255,0,0)"));
+                let _ = ctx.fill_text(load_string, 300.0, 350.0);
+
+                ctx.set_font("64px arial");
+                let load_string = "Click to continue".to_string();
+                utils::drop_shadow_string(ctx, load_string, 300.0, 450.0);
+            },
+            GameState::Paused => {
+                ctx.set_fill_style(&JsValue::from("rgba(0, 0, 0, 0.5)"));
+                ctx.fill_rect(0.0, 0.0, GAME_WIDTH, GAME_HEIGHT);
+
+                ctx.set_font("128px arial");
+                utils::drop_shadow_string(ctx, "PAUSED".to_string(), 380.0, 350.0);
+                ctx.set_font("64px arial");
+                utils::drop_shadow_string(ctx, "Press P to resume".to_string(), 360.0, 450.0);
+                ctx.set_font("32px arial");
+                utils::drop_shadow_string(ctx, "Press M for the level select".to_string(), 430.0, 520.0);
+            },
+            GameState::Finished => {
+                if !self.results.is_empty() {
+                    self.results.render(ctx);
+                } else if self.images.is_empty() {
+                    // No credits art to show, so say it ourselves
+                    ctx.set_font("128px arial");
+                    utils::drop_shadow_string(ctx, "You finished!".to_string(), 240.0, 400.0);
+                }
+                ctx.set_font("32px arial");
+                utils::drop_shadow_string(ctx, "Click to play again".to_string(), 500.0, 750.0);
+            },
+            GameState::Menu => {
+                if let Some(level_select) = &self.level_select {
+                    level_select.render(ctx, &self.progress);
+                }
+            },
+            GameState::Error => {
+                ctx.set_font("64px arial");
+                utils::drop_shadow_string(ctx, "Could not load level".to_string(), 320.0, 300.0);
+                ctx.set_font("24px arial");
+                if let Some(err) = &self.load_error {
+                    utils::drop_shadow_string(ctx, err.to_string(), 100.0, 380.0);
+                }
+
+                ctx.set_font("48px arial");
+                for (button, label) in [(RETRY_BUTTON, "Retry"), (MENU_BUTTON, "Menu")] {
+                    ctx.set_fill_style(&JsValue::from("rgb(65, 65, 155)"));
+                    ctx.fill_rect(button.0, button.1, button.2, button.3);
+                    utils::drop_shadow_string(ctx, label.to_string(), button.0 + 20.0, button.1 + 62.0);
+                }
+            }
+        }
     }
 
     fn get_time_str(&self) -> String {
-        if self.goal.circle.loc.x > GAME_WIDTH || self.goal.circle.loc.y > GAME_HEIGHT {
-            // TODO - add in results table 
+        if self.state == GameState::Finished {
+            // The results table shows the times instead
             return "".to_string();
         }
 
-        let time_sec = (self.cur_time / 1000.0).floor();
-        let time_ms = ((self.cur_time - (time_sec * 1000.0)) / 10.0).floor();
-        let time_str_sec = utils::format_time_3_digits(time_sec);
-        let time_str_ms = utils::format_time_2_digits(time_ms);
-
-        format!("Time: {}.{}",time_str_sec, time_str_ms)
+        format!("Time: {}", utils::format_time(self.sim.elapsed()))
     }
-}
\ No newline at end of file
+}
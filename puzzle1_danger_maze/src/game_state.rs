@@ -0,0 +1,96 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameState {
+    // Waiting for the level file
+    Loading,
+    // Level is shown but the player hasn't been picked up yet
+    Ready,
+    Playing,
+    // Short reset animation after touching a hazard
+    Dead,
+    Won,
+    Paused,
+    // Nothing left to play
+    Finished,
+    // The level file couldn't be loaded
+    Error,
+    // Choosing a level from the level select grid
+    Menu
+}
+
+impl GameState {
+    pub fn can_move_to(&self, next: GameState) -> bool {
+        use GameState::*;
+        match (self, next) {
+            // A level can always be (re)loaded
+            (_, Loading) => true,
+            (Loading, Ready) | (Loading, Finished) | (Loading, Error) => true,
+            // Hazards keep moving while the player waits, so they can be hit before moving
+            (Ready, Playing) | (Ready, Paused) | (Ready, Dead) => true,
+            (Playing, Dead) | (Playing, Won) | (Playing, Paused) => true,
+            (Dead, Playing) | (Dead, Paused) => true,
+            (Paused, Ready) | (Paused, Playing) => true,
+            (Won, Finished) => true,
+            // Only Menu itself can't go to the menu
+            (Menu, Menu) => false,
+            (_, Menu) => true,
+            _ => false
+        }
+    }
+
+    // Whether the simulation should be stepped in this state
+    pub fn is_running(&self) -> bool {
+        matches!(self, GameState::Ready | GameState::Playing | GameState::Dead | GameState::Won)
+    }
+
+    // Whether pointer presses should reach the player
+    pub fn accepts_input(&self) -> bool {
+        matches!(self, GameState::Ready | GameState::Playing)
+    }
+
+    pub fn can_pause(&self) -> bool {
+        self.can_move_to(GameState::Paused)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normal_run_through_a_level() {
+        let path = [
+            GameState::Loading,
+            GameState::Ready,
+            GameState::Playing,
+            GameState::Dead,
+            GameState::Playing,
+            GameState::Won,
+            GameState::Loading,
+            GameState::Finished
+        ];
+        for pair in path.windows(2) {
+            assert!(pair[0].can_move_to(pair[1]), "{:?} -> {:?}", pair[0], pair[1]);
+        }
+    }
+
+    #[test]
+    fn menu_and_deaths_from_anywhere_in_a_level() {
+        assert!(GameState::Ready.can_move_to(GameState::Dead));
+        for state in [GameState::Ready, GameState::Playing, GameState::Dead, GameState::Paused] {
+            assert!(state.can_move_to(GameState::Menu), "{:?}", state);
+        }
+    }
+
+    #[test]
+    fn invalid_transitions() {
+        assert!(!GameState::Ready.can_move_to(GameState::Won));
+        assert!(!GameState::Paused.can_move_to(GameState::Won));
+        assert!(!GameState::Won.can_move_to(GameState::Paused));
+        assert!(!GameState::Finished.can_move_to(GameState::Playing));
+        assert!(!GameState::Loading.can_pause());
+        assert!(!GameState::Error.can_move_to(GameState::Ready));
+        assert!(!GameState::Dead.can_move_to(GameState::Dead));
+        assert!(!GameState::Menu.can_move_to(GameState::Menu));
+        assert!(!GameState::Menu.can_move_to(GameState::Playing));
+    }
+}
@@ -0,0 +1,588 @@
+use crate::utils::{self, Circle, Point, Rect};
+
+// Exact collision tests between the shapes in utils. Everything in here is
+// plain maths so it can be tested natively without a browser.
+
+// Same area, but with the origin at the top left so width and height are positive
+pub fn normalise_rect(rect: &Rect<f64>) -> Rect<f64> {
+    Rect::new(
+        rect.loc.x.min(rect.loc.x + rect.width),
+        rect.loc.y.min(rect.loc.y + rect.height),
+        rect.width.abs(),
+        rect.height.abs()
+    )
+}
+
+pub fn point_in_rect(pt: &Point<f64>, rect: &Rect<f64>) -> bool {
+    pt.x > rect.loc.x && pt.x < rect.loc.x + rect.width
+        && pt.y > rect.loc.y && pt.y < rect.loc.y + rect.height
+}
+
+pub fn point_in_circle(pt: &Point<f64>, circle: &Circle<f64>) -> bool {
+    utils::dist_between_points(*pt, circle.loc) < circle.size
+}
+
+pub fn circle_intersects_circle(a: &Circle<f64>, b: &Circle<f64>) -> bool {
+    utils::dist_between_points(a.loc, b.loc) < a.size + b.size
+}
+
+pub fn circle_intersects_rect(circle: &Circle<f64>, rect: &Rect<f64>) -> bool {
+    dist_point_to_rect(&circle.loc, rect) < circle.size
+}
+
+// Shortest distance from pt to any point in the rect, zero if inside
+pub fn dist_point_to_rect(pt: &Point<f64>, rect: &Rect<f64>) -> f64 {
+    // A rect with a negative size would make clamp() panic
+    let rect = normalise_rect(rect);
+    let closest_x = pt.x.max(rect.loc.x).min(rect.loc.x + rect.width);
+    let closest_y = pt.y.max(rect.loc.y).min(rect.loc.y + rect.height);
+    utils::dist_between_points(*pt, Point::new(closest_x, closest_y))
+}
+
+pub fn dist_point_to_segment(pt: &Point<f64>, start: &Point<f64>, end: &Point<f64>) -> f64 {
+    let seg_x = end.x - start.x;
+    let seg_y = end.y - start.y;
+    let len_sq = (seg_x*seg_x) + (seg_y*seg_y);
+    // Project onto the segment, clamped so we stay between the end points
+    let t = if len_sq == 0.0 {
+        0.0
+    } else {
+        (((pt.x - start.x)*seg_x + (pt.y - start.y)*seg_y) / len_sq).clamp(0.0, 1.0)
+    };
+    utils::dist_between_points(*pt, Point::new(start.x + seg_x*t, start.y + seg_y*t))
+}
+
+pub fn segment_intersects_rect(start: &Point<f64>, end: &Point<f64>, rect: &Rect<f64>) -> bool {
+    // Liang-Barsky clip of the segment against the rect
+    let diff_x = end.x - start.x;
+    let diff_y = end.y - start.y;
+    let edges = [
+        (-diff_x, start.x - rect.loc.x),
+        (diff_x, rect.loc.x + rect.width - start.x),
+        (-diff_y, start.y - rect.loc.y),
+        (diff_y, rect.loc.y + rect.height - start.y)
+    ];
+    let mut t_enter = 0.0;
+    let mut t_exit = 1.0;
+    for (p, q) in edges {
+        if p == 0.0 {
+            if q < 0.0 {
+                return false;
+            }
+            continue;
+        }
+        let t = q / p;
+        if p < 0.0 {
+            if t > t_exit {
+                return false;
+            }
+            t_enter = f64::max(t_enter, t);
+        } else {
+            if t < t_enter {
+                return false;
+            }
+            t_exit = f64::min(t_exit, t);
+        }
+    }
+    true
+}
+
+// The capsule is the area covered by a circle of `radius` moving from start to end
+pub fn capsule_intersects_rect(start: &Point<f64>, end: &Point<f64>, radius: f64, rect: &Rect<f64>) -> bool {
+    let rect = &normalise_rect(rect);
+    if segment_intersects_rect(start, end, rect) {
+        return true;
+    }
+    if dist_point_to_rect(start, rect) < radius || dist_point_to_rect(end, rect) < radius {
+        return true;
+    }
+    // Otherwise the closest approach is from one of the corners
+    let corners = [
+        Point::new(rect.loc.x, rect.loc.y),
+        Point::new(rect.loc.x + rect.width, rect.loc.y),
+        Point::new(rect.loc.x, rect.loc.y + rect.height),
+        Point::new(rect.loc.x + rect.width, rect.loc.y + rect.height)
+    ];
+    corners.iter().any(|corner| dist_point_to_segment(corner, start, end) < radius)
+}
+
+pub fn capsule_intersects_circle(start: &Point<f64>, end: &Point<f64>, radius: f64, circle: &Circle<f64>) -> bool {
+    dist_point_to_segment(&circle.loc, start, end) < circle.size + radius
+}
+
+// Where a circle at centre ends up once pushed the shortest way out of the
+// rect, so it is just touching. Unchanged if it wasn't overlapping.
+pub fn push_circle_out_of_rect(centre: &Point<f64>, radius: f64, rect: &Rect<f64>) -> Point<f64> {
+    let rect = &normalise_rect(rect);
+    let closest_x = centre.x.max(rect.loc.x).min(rect.loc.x + rect.width);
+    let closest_y = centre.y.max(rect.loc.y).min(rect.loc.y + rect.height);
+    let dist = utils::dist_between_points(*centre, Point::new(closest_x, closest_y));
+    if dist >= radius {
+        return *centre;
+    }
+    if dist > 0.0 {
+        // Straight away from the nearest point on the edge
+        let push = (radius - dist) / dist;
+        return Point::new(centre.x + (centre.x - closest_x) * push, centre.y + (centre.y - closest_y) * push);
+    }
+    // Centre is inside, so go out through the nearest side
+    let sides = [
+        (centre.x - rect.loc.x, Point::new(rect.loc.x - radius, centre.y)),
+        (rect.loc.x + rect.width - centre.x, Point::new(rect.loc.x + rect.width + radius, centre.y)),
+        (centre.y - rect.loc.y, Point::new(centre.x, rect.loc.y - radius)),
+        (rect.loc.y + rect.height - centre.y, Point::new(centre.x, rect.loc.y + rect.height + radius))
+    ];
+    sides.iter().fold(sides[0], |best, side| if side.0 < best.0 { *side } else { best }).1
+}
+
+// Move a circle from start towards end, stopping at the rects and sliding
+// along them. Goes in steps of at most half the radius so it can never get
+// far enough into a thin rect to come out the other side.
+pub fn slide_circle(start: &Point<f64>, end: &Point<f64>, radius: f64, rects: &[Rect<f64>]) -> Point<f64> {
+    *slide_circle_path(start, end, radius, rects).last().unwrap_or(start)
+}
+
+// As slide_circle, but every point where the circle changed direction on the
+// way, from start to where it stopped, so the whole path can be swept
+pub fn slide_circle_path(start: &Point<f64>, end: &Point<f64>, radius: f64, rects: &[Rect<f64>]) -> Vec::<Point<f64>> {
+    if rects.is_empty() || radius <= 0.0 {
+        return vec![*start, *end];
+    }
+    let steps = (utils::dist_between_points(*start, *end) / (radius / 2.0)).ceil().max(1.0);
+    // Every step goes the same way. A wall only takes away the part of it
+    // going into the wall, so the rest slides along it.
+    let step = Point::new((end.x - start.x) / steps, (end.y - start.y) / steps);
+    let mut path = vec![*start];
+    let mut at = *start;
+    for _ in 0..steps as usize {
+        let before = at;
+        at = Point::new(at.x + step.x, at.y + step.y);
+        // Twice round, for corners where pushing out of one rect goes into another
+        for _ in 0..2 {
+            for rect in rects.iter() {
+                at = push_circle_out_of_rect(&at, radius, rect);
+            }
+        }
+        // Stuck against a wall
+        if utils::dist_between_points(at, before) < 1e-9 {
+            break;
+        }
+        // Still going the same way, so the last point can be moved on
+        if path.len() >= 2 {
+            let (a, b) = (path[path.len() - 2], path[path.len() - 1]);
+            let turn = (b.x - a.x) * (at.y - b.y) - (b.y - a.y) * (at.x - b.x);
+            if turn.abs() < 1e-9 {
+                path.pop();
+            }
+        }
+        path.push(at);
+    }
+    if path.len() == 1 {
+        path.push(at);
+    }
+    path
+}
+
+// A rect turned by `angle` radians about its centre. Tests against it are
+// done by turning the other shape back the other way, so the axis aligned
+// tests above can be used as they are.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OrientedRect {
+    pub centre: Point<f64>,
+    pub width: f64,
+    pub height: f64,
+    pub angle: f64
+}
+
+impl OrientedRect {
+    pub fn new(centre: Point<f64>, width: f64, height: f64, angle: f64) -> Self {
+        OrientedRect {
+            centre,
+            width,
+            height,
+            angle
+        }
+    }
+
+    // Where pt is relative to the rect, with the rect unturned and centred on the origin
+    pub fn to_local(&self, pt: &Point<f64>) -> Point<f64> {
+        let (sin, cos) = self.angle.sin_cos();
+        let x = pt.x - self.centre.x;
+        let y = pt.y - self.centre.y;
+        Point::new(x * cos + y * sin, y * cos - x * sin)
+    }
+
+    pub fn local_rect(&self) -> Rect<f64> {
+        Rect::new(-self.width / 2.0, -self.height / 2.0, self.width, self.height)
+    }
+
+    // Clockwise on screen, starting from the unturned top left
+    pub fn corners(&self) -> [Point<f64>; 4] {
+        let (sin, cos) = self.angle.sin_cos();
+        let (w, h) = (self.width / 2.0, self.height / 2.0);
+        [(-w, -h), (w, -h), (w, h), (-w, h)].map(|(x, y)| {
+            Point::new(self.centre.x + x * cos - y * sin, self.centre.y + x * sin + y * cos)
+        })
+    }
+}
+
+pub fn dist_point_to_oriented_rect(pt: &Point<f64>, rect: &OrientedRect) -> f64 {
+    dist_point_to_rect(&rect.to_local(pt), &rect.local_rect())
+}
+
+pub fn circle_intersects_oriented_rect(circle: &Circle<f64>, rect: &OrientedRect) -> bool {
+    dist_point_to_oriented_rect(&circle.loc, rect) < circle.size
+}
+
+pub fn capsule_intersects_oriented_rect(start: &Point<f64>, end: &Point<f64>, radius: f64, rect: &OrientedRect) -> bool {
+    capsule_intersects_rect(&rect.to_local(start), &rect.to_local(end), radius, &rect.local_rect())
+}
+
+// Polygons are given as their corners in order, closing back to the first
+
+// Even-odd rule. Cast a ray to the right and count the edges it crosses.
+pub fn point_in_polygon(pt: &Point<f64>, points: &[Point<f64>]) -> bool {
+    let mut inside = false;
+    for i in 0..points.len() {
+        let (a, b) = (points[i], points[(i + 1) % points.len()]);
+        if (a.y > pt.y) != (b.y > pt.y) {
+            let cross_x = a.x + (pt.y - a.y) / (b.y - a.y) * (b.x - a.x);
+            if pt.x < cross_x {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+// Shortest distance from pt to the outline, zero if inside
+pub fn dist_point_to_polygon(pt: &Point<f64>, points: &[Point<f64>]) -> f64 {
+    if point_in_polygon(pt, points) {
+        return 0.0;
+    }
+    (0..points.len())
+        .map(|i| dist_point_to_segment(pt, &points[i], &points[(i + 1) % points.len()]))
+        .fold(f64::INFINITY, f64::min)
+}
+
+pub fn circle_intersects_polygon(circle: &Circle<f64>, points: &[Point<f64>]) -> bool {
+    dist_point_to_polygon(&circle.loc, points) < circle.size
+}
+
+// Only counts proper crossings, not segments that just touch or overlap
+pub fn segments_cross(a: &Point<f64>, b: &Point<f64>, c: &Point<f64>, d: &Point<f64>) -> bool {
+    // Which side of the other segment each end is on
+    let side = |p: &Point<f64>, q: &Point<f64>, r: &Point<f64>| (q.x - p.x) * (r.y - p.y) - (q.y - p.y) * (r.x - p.x);
+    let (d1, d2) = (side(c, d, a), side(c, d, b));
+    let (d3, d4) = (side(a, b, c), side(a, b, d));
+    d1 * d2 < 0.0 && d3 * d4 < 0.0
+}
+
+// Zero if they cross, otherwise the closest approach is from one of the ends
+pub fn dist_segment_to_segment(a: &Point<f64>, b: &Point<f64>, c: &Point<f64>, d: &Point<f64>) -> f64 {
+    if segments_cross(a, b, c, d) {
+        return 0.0;
+    }
+    dist_point_to_segment(a, c, d)
+        .min(dist_point_to_segment(b, c, d))
+        .min(dist_point_to_segment(c, a, b))
+        .min(dist_point_to_segment(d, a, b))
+}
+
+pub fn capsule_intersects_polygon(start: &Point<f64>, end: &Point<f64>, radius: f64, points: &[Point<f64>]) -> bool {
+    if point_in_polygon(start, points) || point_in_polygon(end, points) {
+        return true;
+    }
+    (0..points.len()).any(|i| {
+        let (a, b) = (&points[i], &points[(i + 1) % points.len()]);
+        // Segments that don't cross are closest at one of their ends
+        segments_cross(start, end, a, b)
+            || dist_point_to_segment(start, a, b) < radius
+            || dist_point_to_segment(end, a, b) < radius
+            || dist_point_to_segment(a, start, end) < radius
+            || dist_point_to_segment(b, start, end) < radius
+    })
+}
+
+// Twice the signed area, zero for a line or a single point
+pub fn polygon_area(points: &[Point<f64>]) -> f64 {
+    let twice: f64 = (0..points.len())
+        .map(|i| {
+            let (a, b) = (points[i], points[(i + 1) % points.len()]);
+            a.x * b.y - b.x * a.y
+        })
+        .sum();
+    twice / 2.0
+}
+
+// Whether any two edges that aren't next to each other cross
+pub fn polygon_crosses_itself(points: &[Point<f64>]) -> bool {
+    let n = points.len();
+    (0..n).any(|i| {
+        (i + 2..n).any(|j| {
+            // The first and last edges share a corner
+            (i != 0 || j != n - 1)
+                && segments_cross(&points[i], &points[(i + 1) % n], &points[j], &points[(j + 1) % n])
+        })
+    })
+}
+
+// Smallest convex polygon around the points, corners in order
+pub fn convex_hull(points: &[Point<f64>]) -> Vec::<Point<f64>> {
+    let mut sorted = points.to_vec();
+    sorted.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    if sorted.len() < 3 {
+        return sorted;
+    }
+    let turn = |o: &Point<f64>, a: &Point<f64>, b: &Point<f64>| (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x);
+    // Lower half left to right, then the upper half back again
+    let mut hull = Vec::<Point<f64>>::new();
+    for pass in [sorted.clone(), sorted.iter().rev().copied().collect()] {
+        let floor = hull.len();
+        for pt in pass {
+            while hull.len() >= floor + 2 && turn(&hull[hull.len() - 2], &hull[hull.len() - 1], &pt) <= 0.0 {
+                hull.pop();
+            }
+            hull.push(pt);
+        }
+        // The last point starts the other half
+        hull.pop();
+    }
+    hull
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn point_tests() {
+        let rect = Rect::new(10.0, 10.0, 20.0, 10.0);
+        assert!(point_in_rect(&Point::new(15.0, 15.0), &rect));
+        assert!(!point_in_rect(&Point::new(35.0, 15.0), &rect));
+        assert!(!point_in_rect(&Point::new(15.0, 25.0), &rect));
+
+        let circle = Circle::new(0.0, 0.0, 10.0);
+        assert!(point_in_circle(&Point::new(6.0, 6.0), &circle));
+        assert!(!point_in_circle(&Point::new(8.0, 8.0), &circle));
+    }
+
+    #[test]
+    fn normalise_negative_rect() {
+        // Dragged up and to the left from (300, 300)
+        let rect = normalise_rect(&Rect::new(300.0, 300.0, -100.0, -50.0));
+        assert_eq!(rect, Rect::new(200.0, 250.0, 100.0, 50.0));
+        assert!(point_in_rect(&Point::new(250.0, 270.0), &rect));
+
+        let rect = Rect::new(10.0, 10.0, 20.0, 10.0);
+        assert_eq!(normalise_rect(&rect), rect);
+    }
+
+    #[test]
+    fn circle_circle() {
+        let a = Circle::new(0.0, 0.0, 10.0);
+        assert!(circle_intersects_circle(&a, &Circle::new(25.0, 0.0, 20.0)));
+        assert!(!circle_intersects_circle(&a, &Circle::new(35.0, 0.0, 20.0)));
+    }
+
+    #[test]
+    fn circle_rect_corner() {
+        // A corner poking into the circle between any eight sample points
+        let rect = Rect::new(100.0, 100.0, 50.0, 50.0);
+        let circle = Circle::new(82.0, 93.0, 20.0);
+        assert!(circle_intersects_rect(&circle, &rect));
+
+        let circle = Circle::new(80.0, 92.0, 20.0);
+        assert!(!circle_intersects_rect(&circle, &rect));
+    }
+
+    #[test]
+    fn circle_rect_edges_and_inside() {
+        let rect = Rect::new(100.0, 100.0, 50.0, 50.0);
+        assert!(circle_intersects_rect(&Circle::new(125.0, 85.0, 20.0), &rect));
+        assert!(!circle_intersects_rect(&Circle::new(125.0, 75.0, 20.0), &rect));
+        assert!(circle_intersects_rect(&Circle::new(125.0, 125.0, 5.0), &rect));
+    }
+
+    #[test]
+    fn negative_rect_distance() {
+        // Same area as 100,100 50x50 but drawn from the bottom right
+        let rect = Rect::new(150.0, 150.0, -50.0, -50.0);
+        assert_eq!(dist_point_to_rect(&Point::new(125.0, 125.0), &rect), 0.0);
+        assert_eq!(dist_point_to_rect(&Point::new(125.0, 80.0), &rect), 20.0);
+        assert!(circle_intersects_rect(&Circle::new(125.0, 85.0, 20.0), &rect));
+        assert!(!circle_intersects_rect(&Circle::new(125.0, 75.0, 20.0), &rect));
+    }
+
+    #[test]
+    fn capsule_through_thin_rect() {
+        // Thin wall the player jumps straight over in one frame
+        let wall = Rect::new(200.0, 0.0, 5.0, 400.0);
+        let start = Point::new(100.0, 200.0);
+        let end = Point::new(300.0, 200.0);
+        assert!(!circle_intersects_rect(&Circle::new(end.x, end.y, 20.0), &wall));
+        assert!(capsule_intersects_rect(&start, &end, 20.0, &wall));
+
+        // Moving alongside the wall without touching it
+        let start = Point::new(150.0, 100.0);
+        let end = Point::new(150.0, 300.0);
+        assert!(!capsule_intersects_rect(&start, &end, 20.0, &wall));
+    }
+
+    #[test]
+    fn capsule_through_negative_rect() {
+        let rect = Rect::new(110.0, 200.0, -10.0, -200.0);
+        assert!(capsule_intersects_rect(&Point::new(50.0, 100.0), &Point::new(150.0, 100.0), 5.0, &rect));
+        assert!(!capsule_intersects_rect(&Point::new(50.0, 100.0), &Point::new(90.0, 100.0), 5.0, &rect));
+        let pushed = push_circle_out_of_rect(&Point::new(95.0, 100.0), 10.0, &rect);
+        assert_eq!(pushed, Point::new(90.0, 100.0));
+    }
+
+    #[test]
+    fn capsule_past_corner() {
+        let rect = Rect::new(100.0, 100.0, 50.0, 50.0);
+        // Diagonal path that clips the top left corner
+        assert!(capsule_intersects_rect(&Point::new(60.0, 140.0), &Point::new(140.0, 60.0), 20.0, &rect));
+        assert!(!capsule_intersects_rect(&Point::new(40.0, 120.0), &Point::new(120.0, 40.0), 20.0, &rect));
+    }
+
+    #[test]
+    fn capsule_circle() {
+        let circle = Circle::new(200.0, 200.0, 10.0);
+        assert!(capsule_intersects_circle(&Point::new(100.0, 200.0), &Point::new(300.0, 200.0), 20.0, &circle));
+        assert!(!capsule_intersects_circle(&Point::new(100.0, 150.0), &Point::new(300.0, 150.0), 20.0, &circle));
+        // Stationary capsule is just a circle test
+        let pt = Point::new(225.0, 200.0);
+        assert!(capsule_intersects_circle(&pt, &pt, 20.0, &circle));
+    }
+
+    #[test]
+    fn push_out_of_rect() {
+        let rect = Rect::new(100.0, 100.0, 100.0, 50.0);
+        let out = push_circle_out_of_rect(&Point::new(150.0, 90.0), 20.0, &rect);
+        assert_eq!(out, Point::new(150.0, 80.0));
+        // Inside, nearest the bottom
+        let out = push_circle_out_of_rect(&Point::new(120.0, 140.0), 20.0, &rect);
+        assert_eq!(out, Point::new(120.0, 170.0));
+        let clear = Point::new(150.0, 70.0);
+        assert_eq!(push_circle_out_of_rect(&clear, 20.0, &rect), clear);
+    }
+
+    #[test]
+    fn slide_along_a_wall() {
+        let wall = [Rect::new(200.0, 0.0, 5.0, 400.0)];
+        // Straight at a thin wall in one go stops against it
+        let at = slide_circle(&Point::new(100.0, 200.0), &Point::new(300.0, 200.0), 20.0, &wall);
+        assert!((at.x - 180.0).abs() < 1e-9 && (at.y - 200.0).abs() < 1e-9);
+
+        // At an angle, it slides down the wall as far as it can
+        let at = slide_circle(&Point::new(100.0, 100.0), &Point::new(300.0, 300.0), 20.0, &wall);
+        assert!((at.x - 180.0).abs() < 1e-9);
+        assert!((at.y - 300.0).abs() < 1e-9);
+
+        // Rounding the end it slides off the bottom corner, losing some ground
+        let at = slide_circle(&Point::new(180.0, 380.0), &Point::new(300.0, 500.0), 20.0, &wall);
+        assert!(at.x > 205.0 && at.x < 300.0 && at.y > 400.0);
+        // Once past it nothing is in the way
+        let at = slide_circle(&Point::new(180.0, 420.0), &Point::new(300.0, 540.0), 20.0, &wall);
+        assert!(utils::dist_between_points(at, Point::new(300.0, 540.0)) < 1e-9);
+    }
+
+    #[test]
+    fn slide_path_turns_at_the_wall() {
+        // Diagonally into a wall, then down along it
+        let wall = Rect::new(100.0, 0.0, 20.0, 400.0);
+        let path = slide_circle_path(&Point::new(0.0, 100.0), &Point::new(200.0, 300.0), 10.0, &[wall]);
+        assert_eq!(path[0], Point::new(0.0, 100.0));
+        assert_eq!(*path.last().unwrap(), slide_circle(&Point::new(0.0, 100.0), &Point::new(200.0, 300.0), 10.0, &[wall]));
+        // Straight parts are only a start and an end, however many steps they took
+        assert!(path.len() <= 4, "{:?}", path);
+        assert!(path.iter().all(|p| p.x <= 90.0 + 1e-9));
+        assert!(path.iter().any(|p| p.x > 89.0 && p.y < 200.0));
+
+        assert_eq!(slide_circle_path(&Point::new(0.0, 0.0), &Point::new(50.0, 0.0), 10.0, &[]), vec![Point::new(0.0, 0.0), Point::new(50.0, 0.0)]);
+    }
+
+    #[test]
+    fn oriented_rect() {
+        // A 200 x 20 bar turned to point down and to the right
+        let bar = OrientedRect::new(Point::new(100.0, 100.0), 200.0, 20.0, std::f64::consts::FRAC_PI_4);
+        let corners = bar.corners();
+        let local = bar.to_local(&corners[2]);
+        assert!((local.x - 100.0).abs() < 1e-9 && (local.y - 10.0).abs() < 1e-9);
+
+        // Along the bar, but outside the axis aligned box it would be unturned
+        assert!(circle_intersects_oriented_rect(&Circle::new(160.0, 160.0, 5.0), &bar));
+        assert!(!circle_intersects_oriented_rect(&Circle::new(190.0, 100.0, 5.0), &bar));
+        // Beside the bar, just out of reach
+        assert!((dist_point_to_oriented_rect(&Point::new(120.0, 80.0), &bar) - (800.0f64.sqrt() - 10.0)).abs() < 1e-9);
+
+        // Straight across the middle of it
+        assert!(capsule_intersects_oriented_rect(&Point::new(200.0, 0.0), &Point::new(0.0, 200.0), 5.0, &bar));
+        assert!(!capsule_intersects_oriented_rect(&Point::new(200.0, 0.0), &Point::new(250.0, 50.0), 5.0, &bar));
+    }
+
+    // An L shape with the notch at the top right
+    fn notched() -> Vec::<Point<f64>> {
+        [(0.0, 0.0), (100.0, 0.0), (100.0, 100.0), (200.0, 100.0), (200.0, 200.0), (0.0, 200.0)]
+            .iter().map(|(x, y)| Point::new(*x, *y)).collect()
+    }
+
+    #[test]
+    fn concave_polygon() {
+        let shape = notched();
+        assert!(point_in_polygon(&Point::new(50.0, 50.0), &shape));
+        assert!(point_in_polygon(&Point::new(150.0, 150.0), &shape));
+        assert!(!point_in_polygon(&Point::new(150.0, 50.0), &shape));
+        assert_eq!(polygon_area(&shape), 30000.0);
+
+        // In the notch, near the inside corner
+        assert!((dist_point_to_polygon(&Point::new(120.0, 80.0), &shape) - 20.0).abs() < 1e-9);
+        assert!(circle_intersects_polygon(&Circle::new(130.0, 70.0, 31.0), &shape));
+        assert!(!circle_intersects_polygon(&Circle::new(130.0, 70.0, 29.0), &shape));
+        assert!(circle_intersects_polygon(&Circle::new(50.0, 150.0, 5.0), &shape));
+    }
+
+    #[test]
+    fn capsule_polygon() {
+        let shape = notched();
+        // Diagonally through the notch without touching either side
+        assert!(!capsule_intersects_polygon(&Point::new(110.0, -50.0), &Point::new(250.0, 90.0), 5.0, &shape));
+        // Straight through the thin part in one step
+        assert!(capsule_intersects_polygon(&Point::new(-50.0, 150.0), &Point::new(250.0, 150.0), 5.0, &shape));
+        // Close past a corner
+        assert!(capsule_intersects_polygon(&Point::new(110.0, -50.0), &Point::new(110.0, 50.0), 15.0, &shape));
+        assert!(!capsule_intersects_polygon(&Point::new(110.0, -50.0), &Point::new(110.0, 50.0), 5.0, &shape));
+    }
+
+    #[test]
+    fn segment_to_segment() {
+        let (a, b) = (Point::new(0.0, 0.0), Point::new(100.0, 0.0));
+        assert_eq!(dist_segment_to_segment(&a, &b, &Point::new(50.0, -10.0), &Point::new(50.0, 10.0)), 0.0);
+        assert_eq!(dist_segment_to_segment(&a, &b, &Point::new(50.0, 20.0), &Point::new(150.0, 20.0)), 20.0);
+        assert_eq!(dist_segment_to_segment(&a, &b, &Point::new(130.0, 40.0), &Point::new(130.0, 90.0)), 50.0);
+    }
+
+    #[test]
+    fn hull_of_a_swept_square() {
+        // A 10px square at both ends of a diagonal move makes a hexagon
+        let mut points = Vec::<Point<f64>>::new();
+        for (x, y) in [(0.0, 0.0), (50.0, 50.0)] {
+            points.extend([Point::new(x, y), Point::new(x + 10.0, y), Point::new(x, y + 10.0), Point::new(x + 10.0, y + 10.0)]);
+        }
+        let hull = convex_hull(&points);
+        assert_eq!(hull.len(), 6);
+        assert!(!hull.contains(&Point::new(10.0, 10.0)));
+        assert!(!hull.contains(&Point::new(50.0, 50.0)));
+        assert!(point_in_polygon(&Point::new(30.0, 30.0), &hull));
+        assert_eq!(polygon_area(&hull), 100.0 + 2.0 * 50.0 * 10.0);
+    }
+
+    #[test]
+    fn crossed_polygon() {
+        assert!(!polygon_crosses_itself(&notched()));
+        let bow = [Point::new(0.0, 0.0), Point::new(100.0, 100.0), Point::new(100.0, 0.0), Point::new(0.0, 100.0)];
+        assert!(polygon_crosses_itself(&bow));
+        assert_eq!(polygon_area(&bow), 0.0);
+    }
+}
@@ -1,31 +1,441 @@
-use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement};
+use web_sys::{Blob, BlobPropertyBag, CanvasRenderingContext2d, File, HtmlAnchorElement, HtmlCanvasElement, HtmlInputElement, HtmlSelectElement, HtmlTextAreaElement, Url};
 use yew::prelude::*;
 use wasm_bindgen::{prelude::*, JsCast};
+use wasm_bindgen_futures::JsFuture;
 use web_sys::window;
 use js_sys::Date;
 use gloo_console::log;
 use std::vec::Vec;
 
+use crate::edit_history::{EditCommand, EditHistory, EditTarget};
 use crate::game_components::goal::Goal;
-use crate::game_components::player::Player;
-use crate::levels::level_model::{LevelBlockModel, LevelCircleModel, LevelModel};
-use crate::utils::Point;
-use crate::{game_components::{danger_block::DangerBlock, danger_circle::DangerCircle, mouse_handler::MouseHandler}, utils};
+use crate::game_components::image::Image;
+use crate::game_components::player::{Player, PLAYER_SIZE};
+use crate::levels::level_loader::{self, LevelLoadError};
+use crate::levels::level_model::{LevelBarModel, LevelImageModel, LevelModel, LevelMotionKind, LevelMotionModel, LevelPointModel, LevelPulseModel};
+use crate::levels::manifest::{self, LevelManifest, DEFAULT_MANIFEST};
+use crate::levels::{solver, validator};
+use crate::game_control::DEAD_TIME;
+use crate::geometry;
+use crate::motion;
+use crate::simulation::{PointerInput, SimEvent, Simulation, SIM_MAX_CATCH_UP, SIM_STEP};
+use crate::snap::{SnapSettings, Snapped};
+use crate::utils::{Circle, Point, Rect};
+use crate::{game_components::{danger_bar::DangerBar, danger_block::DangerBlock, danger_circle::DangerCircle, danger_polygon::DangerPolygon, mouse_handler::MouseHandler, wall::Wall}, utils};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BuilderMode {
+    Blocks,
+    Circles,
+    Player,
+    Goal,
+    Select,
+    Images,
+    Paths,
+    Bars,
+    Polygons,
+    Walls
+}
+
+// Everything in assets/img, which the browser has no way to list for us
+pub const BUILDER_IMAGES: [&str; 7] = [
+    "congratulations.png",
+    "danger_maze_logo.png",
+    "do_not_touch.png",
+    "drag_this.png",
+    "red_loop1.png",
+    "red_loop2.png",
+    "to_here.png"
+];
+const IMAGE_DIR: &str = "./assets/img/";
+// Least time between solves while something is being dragged, in ms
+const SOLVE_INTERVAL: f64 = 150.0;
+// New paths start at this speed, in px per second, and change by SPEED_STEP
+const PATH_SPEED: f64 = 100.0;
+const SPEED_STEP: f64 = 20.0;
+const PHASE_STEP: f64 = 0.125;
+// New bars, and how much - and = change the spin, in degrees per second
+const BAR_THICKNESS: f64 = 20.0;
+const BAR_SPEED: f64 = 90.0;
+const SPIN_STEP: f64 = 30.0;
+const THICKNESS_STEP: f64 = 5.0;
+// U steps a hazard through blinking, breathing and always on, times in ms
+const PULSE_ON: f64 = 1000.0;
+const PULSE_OFF: f64 = 1000.0;
+const PULSE_GROW: f64 = 300.0;
+// How long the downloaded file's URL is kept, in ms, so the browser can start saving it
+const DOWNLOAD_REVOKE_DELAY: i32 = 1000;
+
+// File name to download as, always ending in .json
+pub fn download_name(name: &str) -> String {
+    let name = name.trim().rsplit(['/', '\\']).next().unwrap_or("").trim();
+    if name.is_empty() {
+        "level.json".to_string()
+    } else if name.ends_with(".json") {
+        name.to_string()
+    } else {
+        format!("{}.json", name)
+    }
+}
+
+impl BuilderMode {
+    pub fn from_key(key: &str) -> Option<BuilderMode> {
+        match key {
+            "KeyB" => Some(BuilderMode::Blocks),
+            "KeyC" => Some(BuilderMode::Circles),
+            "KeyP" => Some(BuilderMode::Player),
+            "KeyG" => Some(BuilderMode::Goal),
+            "KeyV" => Some(BuilderMode::Select),
+            "KeyI" => Some(BuilderMode::Images),
+            "KeyW" => Some(BuilderMode::Paths),
+            "KeyR" => Some(BuilderMode::Bars),
+            "KeyF" => Some(BuilderMode::Polygons),
+            "KeyX" => Some(BuilderMode::Walls),
+            _ => None
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            BuilderMode::Blocks => "BLOCKS",
+            BuilderMode::Circles => "CIRCLES",
+            BuilderMode::Player => "PLAYER",
+            BuilderMode::Goal => "GOAL",
+            BuilderMode::Select => "SELECT",
+            BuilderMode::Images => "IMAGES",
+            BuilderMode::Paths => "PATHS",
+            BuilderMode::Bars => "BARS",
+            BuilderMode::Polygons => "POLYGONS",
+            BuilderMode::Walls => "WALLS"
+        }
+    }
+}
+
+// A shape picked in Select mode, by its index in its list
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Selection {
+    Block(usize),
+    Circle(usize),
+    Bar(usize),
+    Polygon(usize),
+    Wall(usize)
+}
+
+// What dragging in Select mode does to the selected shape
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SelectDrag {
+    // Offset from the shape's origin to where it was grabbed
+    Move(Point<f64>),
+    // Corner of the block opposite the handle, which stays put
+    Resize(Point<f64>),
+    Radius,
+    // End of a bar, which sets its length and starting angle
+    Turn,
+    // One corner of a polygon, by its index
+    Corner(usize)
+}
+
+const HANDLE_SIZE: f64 = 14.0;
+
+fn rect_from_points(a: &Point<f64>, b: &Point<f64>) -> Rect<f64> {
+    Rect::new(a.x.min(b.x), a.y.min(b.y), (a.x - b.x).abs(), (a.y - b.y).abs())
+}
+
+// Clockwise from the origin, so the opposite corner is two along
+fn block_corners(rect: &Rect<f64>) -> [Point<f64>; 4] {
+    let (x, y) = (rect.loc.x, rect.loc.y);
+    [
+        Point::new(x, y),
+        Point::new(x + rect.width, y),
+        Point::new(x + rect.width, y + rect.height),
+        Point::new(x, y + rect.height)
+    ]
+}
+
+fn radius_handle(circle: &Circle<f64>) -> Point<f64> {
+    Point::new(circle.loc.x + circle.size, circle.loc.y)
+}
+
+// One end of the bar, at its starting angle
+fn bar_tip(bar: &DangerBar) -> Point<f64> {
+    let angle = bar.start_angle.to_radians();
+    Point::new(bar.pivot.x + angle.cos() * bar.length / 2.0, bar.pivot.y + angle.sin() * bar.length / 2.0)
+}
+
+// Pivot to tip sets half the length and which way the bar starts
+fn bar_from_points(pivot: &Point<f64>, tip: &Point<f64>) -> (f64, f64) {
+    let length = utils::dist_between_points(*pivot, *tip) * 2.0;
+    let angle = (tip.y - pivot.y).atan2(tip.x - pivot.x).to_degrees();
+    (length, angle)
+}
+
+// The shape dragged out from start to end, if the mode draws one
+pub fn drawn_shape(mode: BuilderMode, start: &Point<f64>, end: &Point<f64>) -> Option<EditCommand> {
+    match mode {
+        BuilderMode::Blocks => {
+            let block = DangerBlock::new(start.x, start.y, end.x - start.x, end.y - start.y);
+            Some(EditCommand::AddBlock(block.pos))
+        },
+        BuilderMode::Circles => {
+            let circle = DangerCircle::new(start.x, start.y, utils::dist_between_points(*start, *end));
+            Some(EditCommand::AddCircle(circle.pos))
+        },
+        BuilderMode::Bars => {
+            let (length, angle) = bar_from_points(start, end);
+            Some(EditCommand::AddBar(DangerBar::new(start.x, start.y, length, BAR_THICKNESS, BAR_SPEED, angle).model()))
+        },
+        BuilderMode::Walls => {
+            let wall = Wall::new(start.x, start.y, end.x - start.x, end.y - start.y);
+            Some(EditCommand::AddWall(wall.model()))
+        },
+        _ => None
+    }
+}
+
+fn on_handle(handle: &Point<f64>, pt: &Point<f64>) -> bool {
+    (pt.x - handle.x).abs() <= HANDLE_SIZE / 2.0 && (pt.y - handle.y).abs() <= HANDLE_SIZE / 2.0
+}
+
+// Find what a click at pt grabs, handles on the current selection first
+pub fn pick_shape(blocks: &[DangerBlock], circles: &[DangerCircle], bars: &[DangerBar], polygons: &[DangerPolygon], walls: &[Wall], selected: Option<Selection>, pt: &Point<f64>) -> Option<(Selection, SelectDrag)> {
+    match selected {
+        Some(Selection::Block(i)) if i < blocks.len() => {
+            let corners = block_corners(&blocks[i].pos);
+            if let Some(n) = corners.iter().position(|c| on_handle(c, pt)) {
+                return Some((Selection::Block(i), SelectDrag::Resize(corners[(n + 2) % 4])));
+            }
+        },
+        Some(Selection::Circle(i)) if i < circles.len() && on_handle(&radius_handle(&circles[i].pos), pt) => {
+            return Some((Selection::Circle(i), SelectDrag::Radius));
+        },
+        Some(Selection::Bar(i)) if i < bars.len() && on_handle(&bar_tip(&bars[i]), pt) => {
+            return Some((Selection::Bar(i), SelectDrag::Turn));
+        },
+        Some(Selection::Polygon(i)) if i < polygons.len() => {
+            if let Some(n) = polygons[i].points.iter().position(|p| on_handle(p, pt)) {
+                return Some((Selection::Polygon(i), SelectDrag::Corner(n)));
+            }
+        },
+        Some(Selection::Wall(i)) if i < walls.len() => {
+            let corners = block_corners(&walls[i].pos);
+            if let Some(n) = corners.iter().position(|c| on_handle(c, pt)) {
+                return Some((Selection::Wall(i), SelectDrag::Resize(corners[(n + 2) % 4])));
+            }
+        },
+        _ => {}
+    }
+
+    // Polygons are drawn over bars, bars over circles, circles over blocks,
+    // and later shapes over earlier ones
+    if let Some(i) = (0..polygons.len()).rev().find(|i| polygons[*i].point_inside(pt.x, pt.y)) {
+        let first = polygons[i].points[0];
+        return Some((Selection::Polygon(i), SelectDrag::Move(Point::new(pt.x - first.x, pt.y - first.y))));
+    }
+    if let Some(i) = (0..bars.len()).rev().find(|i| bars[*i].point_inside(pt.x, pt.y)) {
+        let pivot = bars[i].pivot;
+        return Some((Selection::Bar(i), SelectDrag::Move(Point::new(pt.x - pivot.x, pt.y - pivot.y))));
+    }
+    if let Some(i) = (0..circles.len()).rev().find(|i| geometry::point_in_circle(pt, &circles[*i].pos)) {
+        let loc = circles[i].pos.loc;
+        return Some((Selection::Circle(i), SelectDrag::Move(Point::new(pt.x - loc.x, pt.y - loc.y))));
+    }
+    if let Some(i) = (0..blocks.len()).rev().find(|i| blocks[*i].point_inside(pt.x, pt.y)) {
+        let loc = blocks[i].pos.loc;
+        return Some((Selection::Block(i), SelectDrag::Move(Point::new(pt.x - loc.x, pt.y - loc.y))));
+    }
+    // Walls are drawn under everything
+    let wall_at = (0..walls.len()).rev().find(|i| walls[*i].point_inside(pt.x, pt.y));
+    wall_at.map(|i| {
+        let loc = walls[i].pos.loc;
+        (Selection::Wall(i), SelectDrag::Move(Point::new(pt.x - loc.x, pt.y - loc.y)))
+    })
+}
+
+// Move or reshape what pick_shape grabbed
+pub fn drag_shape(blocks: &mut [DangerBlock], circles: &mut [DangerCircle], bars: &mut [DangerBar], polygons: &mut [DangerPolygon], walls: &mut [Wall], grabbed: (Selection, SelectDrag), pt: &Point<f64>) {
+    match grabbed {
+        (Selection::Block(i), SelectDrag::Move(offset)) => {
+            if let Some(block) = blocks.get_mut(i) {
+                let loc = Point::new(pt.x - offset.x, pt.y - offset.y);
+                move_path(&mut block.anim.motion, loc.x - block.pos.loc.x, loc.y - block.pos.loc.y);
+                block.pos.loc = loc;
+            }
+        },
+        (Selection::Block(i), SelectDrag::Resize(anchor)) => {
+            if let Some(block) = blocks.get_mut(i) {
+                block.pos = rect_from_points(&anchor, pt);
+            }
+        },
+        (Selection::Circle(i), SelectDrag::Move(offset)) => {
+            if let Some(circle) = circles.get_mut(i) {
+                let loc = Point::new(pt.x - offset.x, pt.y - offset.y);
+                move_path(&mut circle.anim.motion, loc.x - circle.pos.loc.x, loc.y - circle.pos.loc.y);
+                circle.pos.loc = loc;
+            }
+        },
+        (Selection::Circle(i), SelectDrag::Radius) => {
+            if let Some(circle) = circles.get_mut(i) {
+                circle.pos.size = utils::dist_between_points(circle.pos.loc, *pt).max(1.0);
+            }
+        },
+        (Selection::Bar(i), SelectDrag::Move(offset)) => {
+            if let Some(bar) = bars.get_mut(i) {
+                bar.pivot = Point::new(pt.x - offset.x, pt.y - offset.y);
+            }
+        },
+        (Selection::Bar(i), SelectDrag::Turn) => {
+            if let Some(bar) = bars.get_mut(i) {
+                let (length, angle) = bar_from_points(&bar.pivot, pt);
+                bar.length = length.max(1.0);
+                bar.start_angle = angle;
+                bar.restart();
+            }
+        },
+        (Selection::Polygon(i), SelectDrag::Move(offset)) => {
+            if let Some(polygon) = polygons.get_mut(i) {
+                // The first corner goes where the pointer is, less the grab offset
+                let first = polygon.points[0];
+                polygon.translate(pt.x - offset.x - first.x, pt.y - offset.y - first.y);
+            }
+        },
+        (Selection::Polygon(i), SelectDrag::Corner(n)) => {
+            if let Some(corner) = polygons.get_mut(i).and_then(|p| p.points.get_mut(n)) {
+                *corner = *pt;
+            }
+        },
+        (Selection::Wall(i), SelectDrag::Move(offset)) => {
+            if let Some(wall) = walls.get_mut(i) {
+                wall.pos.loc = Point::new(pt.x - offset.x, pt.y - offset.y);
+            }
+        },
+        (Selection::Wall(i), SelectDrag::Resize(anchor)) => {
+            if let Some(wall) = walls.get_mut(i) {
+                wall.pos = rect_from_points(&anchor, pt);
+            }
+        },
+        _ => {}
+    }
+}
+
+// Waypoints are where the shape goes, not how far, so they have to follow
+// it when it is moved. That includes an orbit's centre.
+fn move_path(motion: &mut Option<LevelMotionModel>, x: f64, y: f64) {
+    for point in motion.iter_mut().flat_map(|m| m.points.iter_mut()) {
+        point.x += x;
+        point.y += y;
+    }
+}
+
+// Start a path at pt, or carry it on to pt. An orbit only has a centre,
+// so that is moved instead.
+pub fn add_waypoint(motion: Option<LevelMotionModel>, pt: &Point<f64>) -> LevelMotionModel {
+    let mut motion = motion.unwrap_or(LevelMotionModel {
+        kind: LevelMotionKind::Linear,
+        points: Vec::new(),
+        speed: PATH_SPEED,
+        phase: 0.0
+    });
+    if motion.kind == LevelMotionKind::Orbit {
+        motion.points.clear();
+    }
+    motion.points.push(LevelPointModel { x: pt.x, y: pt.y });
+    motion
+}
+
+// Take the last point off, the shape stops moving once there are none
+pub fn remove_waypoint(motion: Option<LevelMotionModel>) -> Option<LevelMotionModel> {
+    let mut motion = motion?;
+    motion.points.pop();
+    if motion.points.is_empty() {
+        None
+    } else {
+        Some(motion)
+    }
+}
+
+// Orbits only use the first point, the others are kept in case it changes back
+pub fn next_motion_kind(kind: LevelMotionKind) -> LevelMotionKind {
+    match kind {
+        LevelMotionKind::Linear => LevelMotionKind::Loop,
+        LevelMotionKind::Loop => LevelMotionKind::Orbit,
+        LevelMotionKind::Orbit => LevelMotionKind::Linear
+    }
+}
+
+// Blinks first, then grows and shrinks, then goes back to always on
+pub fn next_pulse(pulse: Option<LevelPulseModel>) -> Option<LevelPulseModel> {
+    match pulse {
+        None => Some(LevelPulseModel { on: PULSE_ON, off: PULSE_OFF, phase: 0.0, grow: 0.0 }),
+        Some(pulse) if pulse.grow <= 0.0 => Some(LevelPulseModel { grow: PULSE_GROW, ..pulse }),
+        Some(_) => None
+    }
+}
+
+fn pulse_label(pulse: &LevelPulseModel) -> String {
+    let kind = if pulse.grow > 0.0 { "BREATHES" } else { "BLINKS" };
+    format!("{} on {:.0}ms off {:.0}ms - U to change", kind, pulse.on, pulse.off)
+}
+
+fn motion_label(kind: LevelMotionKind) -> &'static str {
+    match kind {
+        LevelMotionKind::Linear => "BACK AND FORTH",
+        LevelMotionKind::Loop => "LOOP",
+        LevelMotionKind::Orbit => "ORBIT"
+    }
+}
 
 pub struct LevelBuilder {
-    state: String,
+    state: BuilderMode,
     pub mouse: MouseHandler,
-    start: Point<f64>,
-    end: Point<f64>,
+    // Where the pointer went down, and where it has been dragged to since
+    start: Option<Point<f64>>,
+    end: Option<Point<f64>>,
     player: Player,
     goal: Goal,
     blocks: Vec::<DangerBlock>, 
     circles: Vec::<DangerCircle>,
+    bars: Vec::<DangerBar>,
+    polygons: Vec::<DangerPolygon>,
+    // Corners placed so far for the polygon being drawn
+    polygon_points: Vec::<Point<f64>>,
+    walls: Vec::<Wall>,
+    images: Vec::<Image>,
+    // Index into BUILDER_IMAGES of what the next click places
+    image_choice: usize,
+    selected_image: Option<usize>,
+    // Grab offset and where the image was when the drag started
+    image_drag: Option<(Point<f64>, Point<f64>)>,
+    selected: Option<Selection>,
+    drag: Option<SelectDrag>,
+    // Where the dragged shape started, recorded as one edit on release
+    drag_change: Option<EditCommand>,
+    history: EditHistory,
+    snap: SnapSettings,
+    // Last snap made while dragging, for drawing its guides
+    last_snap: Option<Snapped>,
+    // Level being edited, keeps anything the builder can't change yet
+    level: LevelModel,
+    level_file: String,
+    manifest: Option<LevelManifest>,
+    selected_file: String,
+    json_text: String,
+    io_message: Option<String>,
     canvas: NodeRef,
     callback: Closure<dyn FnMut()>,
     last_update: f64,
     cur_time: f64,
-    show_screen: i32
+    show_screen: i32,
+    // Set by every edit, so the scene is only checked again once it changes
+    scene_dirty: bool,
+    checked_time: f64,
+    scene_warning: Option<String>,
+    solution: Option<solver::Solution>,
+    show_path: bool,
+    // A copy of the scene being played with the game rules, None while editing
+    playtest: Option<Simulation>,
+    playtest_step: f64,
+    // Time left on the red flash after a reset
+    playtest_flash: f64
 }
 
 pub enum LevelBuildMsg {
@@ -37,8 +447,20 @@ pub enum LevelBuildMsg {
     TouchMove((f64, f64)),
     KeyDown(String),
     KeyUp(String),
+    Undo,
+    Redo,
     MousePrevScreen,
     MouseNextScreen,
+    ManifestLoaded(LevelManifest),
+    SelectFile(String),
+    OpenLevel,
+    LevelLoaded((String, LevelModel)),
+    LevelLoadFailed(LevelLoadError),
+    JsonEdited(String),
+    LoadJson,
+    FileNameEdited(String),
+    DownloadLevel,
+    UploadLevel(File),
     Render,
     Null
 }
@@ -60,52 +482,157 @@ impl Component for LevelBuilder {
 
         ctx.link().send_message(LevelBuildMsg::Render);
 
+        // The manifest gives us the list of levels that can be opened
+        let comp_ctx = ctx.link().clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            match manifest::fetch_manifest(DEFAULT_MANIFEST).await {
+                Ok(manifest) => comp_ctx.send_message(LevelBuildMsg::ManifestLoaded(manifest)),
+                Err(err) => log!(err.to_string())
+            }
+        });
+
         LevelBuilder{
-            state: "BLOCKS".to_string(),
+            state: BuilderMode::Blocks,
             mouse: MouseHandler::new(),
-            start: Point::new(0.0,0.0),
-            end: Point::new(0.0,0.0),
+            start: None,
+            end: None,
             player: Player::new(100.0,100.0),
             goal: Goal::new(1000.0, 700.0),
             blocks: Vec::<DangerBlock>::new(), 
             circles: Vec::<DangerCircle>::new(),
+            bars: Vec::<DangerBar>::new(),
+            polygons: Vec::<DangerPolygon>::new(),
+            polygon_points: Vec::<Point<f64>>::new(),
+            walls: Vec::<Wall>::new(),
+            images: Vec::<Image>::new(),
+            image_choice: 0,
+            selected_image: None,
+            image_drag: None,
+            selected: None,
+            drag: None,
+            drag_change: None,
+            history: EditHistory::new(),
+            snap: SnapSettings::default(),
+            last_snap: None,
+            level: LevelModel::new(),
+            level_file: "new_level.json".to_string(),
+            manifest: None,
+            selected_file: "".to_string(),
+            json_text: "".to_string(),
+            io_message: None,
             canvas: NodeRef::default(),
             callback: callback,
             last_update: Date::now(),
             cur_time: 0.0,
-            show_screen: 1
+            show_screen: 1,
+            scene_dirty: true,
+            checked_time: 0.0,
+            scene_warning: None,
+            solution: None,
+            show_path: true,
+            playtest: None,
+            playtest_step: 0.0,
+            playtest_flash: 0.0
         }
     }
 
     fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool{
         match msg {
             LevelBuildMsg::MouseDown(evt) => {
+                if let Some(sim) = self.playtest.as_mut() {
+                    sim.handle_input(PointerInput::Down(evt.0, evt.1));
+                    return true;
+                }
                 self.mouse.mouse_down = true;
 
                 self.mouse.click(evt.0, evt.1);
-                self.start.x = evt.0;
-                self.start.y = evt.1;
+                self.start = Some(Point::new(evt.0, evt.1));
+                self.end = None;
+                if self.drags_out_shapes() {
+                    self.start = Some(self.snap_point(Point::new(evt.0, evt.1), None));
+                }
 
-                if self.state == "PLAYER" {
-                    self.player.loc.x = evt.0;
-                    self.player.loc.y = evt.1;
-                } else if self.state == "GOAL" {
-                    self.goal.circle.loc.x = evt.0;
-                    self.goal.circle.loc.y = evt.1;
+                let marker = if self.snap.markers {
+                    self.snap_point(Point::new(evt.0, evt.1), None)
+                } else {
+                    Point::new(evt.0, evt.1)
+                };
+                if self.state == BuilderMode::Player {
+                    let before = self.player.loc;
+                    self.player.loc = marker;
+                    if before != self.player.loc {
+                        self.record(EditCommand::MovePlayer { before, after: self.player.loc });
+                    }
+                } else if self.state == BuilderMode::Goal {
+                    let before = self.goal.circle.loc;
+                    self.goal.circle.loc = marker;
+                    if before != self.goal.circle.loc {
+                        self.record(EditCommand::MoveGoal { before, after: self.goal.circle.loc });
+                    }
+                } else if self.state == BuilderMode::Select {
+                    let picked = pick_shape(&self.blocks, &self.circles, &self.bars, &self.polygons, &self.walls, self.selected, &Point::new(evt.0, evt.1));
+                    self.selected = picked.map(|(selection, _)| selection);
+                    self.drag = picked.map(|(_, drag)| drag);
+                    self.drag_change = self.selected.and_then(|selection| self.shape_change(selection));
+                } else if self.state == BuilderMode::Images {
+                    self.pick_or_place_image(Point::new(evt.0, evt.1));
+                } else if self.state == BuilderMode::Paths {
+                    self.pick_or_add_waypoint(Point::new(evt.0, evt.1));
+                } else if self.state == BuilderMode::Polygons {
+                    self.add_polygon_point(Point::new(evt.0, evt.1));
                 }
 
                 true
             },
-            LevelBuildMsg::MouseUp(_evt) => {
+            LevelBuildMsg::MouseUp(evt) => {
+                if let Some(sim) = self.playtest.as_mut() {
+                    sim.handle_input(PointerInput::Up(evt.0, evt.1));
+                    return true;
+                }
                 self.mouse.mouse_down = false;
+                self.drag = None;
+                self.last_snap = None;
+                self.finish_drag();
+                self.finish_image_drag();
                 true
             },
             LevelBuildMsg::MouseMove(evt) => {
                 self.mouse.update_pos(evt.0, evt.1);
+                if let Some(sim) = self.playtest.as_mut() {
+                    sim.handle_input(PointerInput::Move(evt.0, evt.1));
+                    return true;
+                }
 
                 if self.mouse.mouse_down {
-                    self.end.x = evt.0;
-                    self.end.y = evt.1;
+                    self.end = Some(Point::new(evt.0, evt.1));
+                    if self.drags_out_shapes() {
+                        self.end = Some(self.snap_point(Point::new(evt.0, evt.1), None));
+                    }
+                }
+
+                if let (Some(selected), Some(drag)) = (self.selected, self.drag) {
+                    let exclude = match selected {
+                        Selection::Block(i) => Some(i),
+                        _ => None
+                    };
+                    // Snap where the shape's origin would go, not the pointer
+                    let pt = match drag {
+                        SelectDrag::Move(offset) => {
+                            let loc = self.snap_point(Point::new(evt.0 - offset.x, evt.1 - offset.y), exclude);
+                            Point::new(loc.x + offset.x, loc.y + offset.y)
+                        },
+                        _ => self.snap_point(Point::new(evt.0, evt.1), exclude)
+                    };
+                    drag_shape(&mut self.blocks, &mut self.circles, &mut self.bars, &mut self.polygons, &mut self.walls, (selected, drag), &pt);
+                    // Recorded when the drag ends, but worth checking along the way
+                    self.scene_dirty = true;
+                }
+
+                if let (Some(index), Some((offset, _))) = (self.selected_image, self.image_drag) {
+                    let loc = self.snap_point(Point::new(evt.0 - offset.x, evt.1 - offset.y), None);
+                    if let Some(image) = self.images.get_mut(index) {
+                        image.loc = loc;
+                    }
                 }
 
                 // log!("Event here => ", self.mouse.loc.x, self.mouse.loc.y);
@@ -127,25 +654,101 @@ impl Component for LevelBuilder {
                 true
             },
             LevelBuildMsg::KeyDown(key) => {
-                if key == "KeyC" {
-                    self.state = "CIRCLES".to_string();
-                } else if key == "KeyB" {
-                    self.state = "BLOCKS".to_string();
-                } else if key == "KeyP" {
-                    self.state = "PLAYER".to_string();
-                } else if key == "KeyG" {
-                    self.state = "GOAL".to_string();
+                if key == "KeyT" {
+                    self.toggle_playtest();
+                } else if self.playtest.is_some() {
+                    // Editing keys do nothing until the playtest is stopped
+                } else if let Some(mode) = BuilderMode::from_key(&key) {
+                    self.state = mode;
+                    self.selected = None;
+                    self.selected_image = None;
+                    self.polygon_points.clear();
                 } else if key == "KeyQ" {
+                    let anything = !self.blocks.is_empty() || !self.circles.is_empty() || !self.bars.is_empty()
+                        || !self.polygons.is_empty() || !self.walls.is_empty() || !self.images.is_empty();
+                    if anything {
+                        self.record(EditCommand::ClearAll {
+                            blocks: self.blocks.iter().map(|b| b.model()).collect(),
+                            circles: self.circles.iter().map(|c| c.model()).collect(),
+                            bars: self.bars.iter().map(|b| b.model()).collect(),
+                            polygons: self.polygons.iter().map(|p| p.model()).collect(),
+                            walls: self.walls.iter().map(|w| w.model()).collect(),
+                            images: self.images.iter().map(Self::image_model).collect()
+                        });
+                    }
                     self.blocks = Vec::new();
                     self.circles = Vec::new();
+                    self.bars = Vec::new();
+                    self.polygons = Vec::new();
+                    self.walls = Vec::new();
+                    self.images = Vec::new();
+                    self.polygon_points.clear();
+                    self.selected = None;
+                    self.selected_image = None;
+                    self.image_drag = None;
+                } else if key == "Enter" && self.state == BuilderMode::Polygons {
+                    self.finish_polygon();
+                } else if (key == "Delete" || key == "Backspace") && !self.polygon_points.is_empty() {
+                    self.polygon_points.pop();
+                } else if (key == "Delete" || key == "Backspace") && self.state == BuilderMode::Paths {
+                    self.edit_motion(remove_waypoint);
+                } else if key == "Delete" || key == "Backspace" {
+                    self.delete_selected();
+                } else if key == "Escape" {
+                    self.selected = None;
+                    self.selected_image = None;
+                    self.polygon_points.clear();
+                } else if matches!(self.selected, Some(Selection::Bar(_))) && (key == "Minus" || key == "Equal") {
+                    let step = if key == "Minus" { -SPIN_STEP } else { SPIN_STEP };
+                    self.edit_bar(|bar| LevelBarModel { speed: bar.speed + step, ..bar });
+                } else if matches!(self.selected, Some(Selection::Bar(_))) && (key == "Comma" || key == "Period") {
+                    let step = if key == "Comma" { -THICKNESS_STEP } else { THICKNESS_STEP };
+                    self.edit_bar(|bar| LevelBarModel { thickness: (bar.thickness + step).max(THICKNESS_STEP), ..bar });
+                } else if key == "KeyU" {
+                    self.edit_pulse(next_pulse);
+                } else if key == "KeyL" {
+                    self.edit_motion(|motion| motion.map(|m| LevelMotionModel { kind: next_motion_kind(m.kind), ..m }));
+                } else if key == "Minus" || key == "Equal" {
+                    let step = if key == "Minus" { -SPEED_STEP } else { SPEED_STEP };
+                    self.edit_motion(|motion| motion.map(|m| LevelMotionModel { speed: (m.speed + step).max(SPEED_STEP), ..m }));
+                } else if key == "Comma" || key == "Period" {
+                    let step = if key == "Comma" { -PHASE_STEP } else { PHASE_STEP };
+                    self.edit_motion(|motion| motion.map(|m| LevelMotionModel { phase: (m.phase + step).rem_euclid(1.0), ..m }));
+                } else if key == "BracketLeft" {
+                    self.image_choice = (self.image_choice + BUILDER_IMAGES.len() - 1) % BUILDER_IMAGES.len();
+                } else if key == "BracketRight" {
+                    self.image_choice = (self.image_choice + 1) % BUILDER_IMAGES.len();
+                } else if key == "KeyO" {
+                    self.show_path = !self.show_path;
+                } else if key == "KeyN" {
+                    self.snap.grid = !self.snap.grid;
+                } else if key == "KeyM" {
+                    self.snap.next_grid_size();
+                } else if key == "KeyH" {
+                    self.snap.guides = !self.snap.guides;
+                } else if key == "KeyK" {
+                    self.snap.markers = !self.snap.markers;
                 } else if key == "KeyS" {
-                    self.save_data();
+                    self.json_text = self.save_data();
+                    self.show_screen = 2;
                 }
                 true
             },
             LevelBuildMsg::KeyUp(_key) => {
                 true
             },
+            LevelBuildMsg::Undo => {
+                if self.playtest.is_none() {
+                    self.step_history(false);
+                }
+                true
+            },
+            LevelBuildMsg::Redo => {
+                if self.playtest.is_none() {
+                    self.step_history(true);
+                }
+                true
+            },
             LevelBuildMsg::MousePrevScreen => {
                 if self.show_screen > 1 {
                     self.show_screen -= 1;
@@ -155,11 +758,95 @@ impl Component for LevelBuilder {
                 true
             },
             LevelBuildMsg::MouseNextScreen => {
+                self.playtest = None;
                 if self.show_screen < 3 {
                     self.show_screen += 1;
                 } else {
                     self.show_screen = 2;
                 }
+                if self.show_screen == 2 {
+                    self.json_text = self.save_data();
+                    self.io_message = None;
+                }
+                true
+            },
+            LevelBuildMsg::ManifestLoaded(manifest) => {
+                if let Some(entry) = manifest.entry(0) {
+                    self.selected_file = entry.file.clone();
+                }
+                self.manifest = Some(manifest);
+                true
+            },
+            LevelBuildMsg::SelectFile(file) => {
+                self.selected_file = file;
+                false
+            },
+            LevelBuildMsg::OpenLevel => {
+                let comp_ctx = ctx.link().clone();
+                let path = self.selected_file.clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    match level_loader::fetch_level(path.as_str()).await {
+                        Ok(level_model) => comp_ctx.send_message(LevelBuildMsg::LevelLoaded((path, level_model))),
+                        Err(err) => comp_ctx.send_message(LevelBuildMsg::LevelLoadFailed(err))
+                    }
+                });
+                false
+            },
+            LevelBuildMsg::LevelLoaded((path, level_model)) => {
+                self.level_file = path.rsplit('/').next().unwrap_or(&path).to_string();
+                self.load_level(level_model);
+                self.io_message = None;
+                self.show_screen = 1;
+                true
+            },
+            LevelBuildMsg::LevelLoadFailed(err) => {
+                log!(err.to_string());
+                self.io_message = Some(err.to_string());
+                true
+            },
+            LevelBuildMsg::JsonEdited(text) => {
+                self.json_text = text;
+                false
+            },
+            LevelBuildMsg::FileNameEdited(name) => {
+                self.level_file = name;
+                false
+            },
+            LevelBuildMsg::DownloadLevel => {
+                self.json_text = self.save_data();
+                if let Err(err) = self.download_level() {
+                    log!(err);
+                    self.io_message = Some("Could not download the level".to_string());
+                }
+                true
+            },
+            LevelBuildMsg::UploadLevel(file) => {
+                let comp_ctx = ctx.link().clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    let name = file.name();
+                    let data = JsFuture::from(file.text()).await.ok().and_then(|text| text.as_string());
+                    let level = match data {
+                        Some(data) => level_loader::parse_json::<LevelModel>(&name, &data),
+                        None => Err(LevelLoadError::Network { path: name.clone(), reason: "the file could not be read".to_string() })
+                    };
+                    match level {
+                        Ok(level_model) => comp_ctx.send_message(LevelBuildMsg::LevelLoaded((name, level_model))),
+                        Err(err) => comp_ctx.send_message(LevelBuildMsg::LevelLoadFailed(err))
+                    }
+                });
+                false
+            },
+            LevelBuildMsg::LoadJson => {
+                match level_loader::parse_json::<LevelModel>(&self.level_file, &self.json_text) {
+                    Ok(level_model) => {
+                        self.load_level(level_model);
+                        self.io_message = None;
+                        self.show_screen = 1;
+                    },
+                    Err(err) => {
+                        self.io_message = Some(err.to_string());
+                    }
+                }
                 true
             },
             LevelBuildMsg::Render => {
@@ -201,7 +888,16 @@ impl Component for LevelBuilder {
             }
         });
         let onkeydown = ctx.link().callback(move |evt: KeyboardEvent| {
-            LevelBuildMsg::KeyDown(evt.code()) 
+            if (evt.ctrl_key() || evt.meta_key()) && evt.code() == "KeyZ" {
+                evt.prevent_default();
+                if evt.shift_key() {
+                    LevelBuildMsg::Redo
+                } else {
+                    LevelBuildMsg::Undo
+                }
+            } else {
+                LevelBuildMsg::KeyDown(evt.code())
+            }
         });
         let onkeyup = ctx.link().callback(move |evt: KeyboardEvent| {
             LevelBuildMsg::KeyUp(evt.code())
@@ -232,7 +928,7 @@ impl Component for LevelBuilder {
                         tabindex = "1"
                     ></canvas><br/>
                 } else if self.show_screen == 2 {
-                    <div><h1>{"Component List"}</h1><h3>{self.get_block_list()}</h3></div>
+                    <div><h1>{"Level File"}</h1>{self.get_level_io(ctx)}</div>
                 } else if self.show_screen == 3 { 
                     <div><h1>{"Key Bindings"}</h1>{self.get_help_string()}</div>
                 }else {
@@ -262,41 +958,37 @@ impl LevelBuilder {
 
         self.mouse.update(diff);
 
+        if let Some(sim) = self.playtest.as_mut() {
+            self.playtest_flash = f64::max(self.playtest_flash - diff, 0.0);
+            // Same fixed steps as GameControl
+            self.playtest_step = f64::min(self.playtest_step + diff, SIM_MAX_CATCH_UP);
+            while self.playtest_step >= SIM_STEP {
+                self.playtest_step -= SIM_STEP;
+                if sim.step(SIM_STEP).contains(&SimEvent::Reset) {
+                    self.playtest_flash = DEAD_TIME;
+                }
+            }
+            return;
+        }
         
-        if self.mouse.mouse_down == false
-             && self.start.x != 0.0 && self.start.y != 0.0 
-             && self.end.x != 0.0 && self.end.y != 0.0 {
-            // Save the new block
-            if self.state == "BLOCKS" {
-                self.blocks.push(
-                    DangerBlock::new(
-                        self.start.x,
-                        self.start.y,
-                        self.end.x - self.start.x,
-                        self.end.y - self.start.y
-                    )
-                );
-            } else if self.state == "CIRCLES" {
-                let rad = utils::dist_between_points(
-                    Point::new(self.start.x, self.start.y),
-                    Point::new(self.end.x, self.end.y)
-                );
-                self.circles.push(
-                    DangerCircle::new(
-                        self.start.x,
-                        self.start.y,
-                        rad
-                    )
-                );
+        // Nothing is drawn by a click that never moved
+        if !self.mouse.mouse_down {
+            if let (Some(start), Some(end)) = (self.start.take(), self.end.take()) {
+                if let Some(command) = drawn_shape(self.state, &start, &end) {
+                    command.apply(&mut EditTarget {
+                        blocks: &mut self.blocks,
+                        circles: &mut self.circles,
+                        bars: &mut self.bars,
+                        polygons: &mut self.polygons,
+                        walls: &mut self.walls,
+                        images: &mut self.images,
+                        player: &mut self.player.loc,
+                        goal: &mut self.goal.circle.loc
+                    });
+                    self.record(command);
+                }
             }
-            
-
-            self.start.x = 0.0;
-            self.start.y = 0.0;
-            self.end.x = 0.0;
-            self.end.y = 0.0;
         }
-    
     }
 
     fn render(&mut self) {
@@ -330,97 +1022,871 @@ impl LevelBuilder {
         ctx.line_to(0.0, 0.0);
         ctx.stroke();
 
+        if self.playtest.is_some() {
+            self.render_playtest(&mut ctx);
+            window()
+                .unwrap()
+                .request_animation_frame(self.callback.as_ref().unchecked_ref())
+                .unwrap();
+            return;
+        }
+
+        if self.snap.grid {
+            self.render_grid(&mut ctx);
+        }
+
         self.mouse.render(&mut ctx);
 
         ctx.set_font("64px arial");
-        utils::drop_shadow_string(&mut ctx, self.state.clone(), 20.0, 780.0);
+        utils::drop_shadow_string(&mut ctx, self.state.label().to_string(), 20.0, 780.0);
+        ctx.set_font("24px arial");
+        utils::drop_shadow_string(&mut ctx, self.snap_status(), 900.0, 780.0);
         
+        for wall in self.walls.iter_mut() {
+            wall.render(&mut ctx);
+        }
         for block in self.blocks.iter_mut() {
             block.render(&mut ctx);
         }
         for circle in self.circles.iter_mut() {
             circle.render(&mut ctx);
         }
+        for bar in self.bars.iter_mut() {
+            bar.render(&mut ctx);
+        }
+        for polygon in self.polygons.iter_mut() {
+            polygon.render(&mut ctx);
+        }
+        for image in self.images.iter_mut() {
+            image.render(&mut ctx);
+        }
+        self.render_motions(&mut ctx);
 
 
-        if self.state == "BLOCKS" {
-            if self.mouse.mouse_down && self.end.x != 0.0 && self.end.y != 0.0 {
-                ctx.set_fill_style(&JsValue::from("rgb(55, 55, 255)"));
+        // The shape being dragged out
+        if let (true, Some(start), Some(end)) = (self.mouse.mouse_down, self.start, self.end) {
+            ctx.set_fill_style(&JsValue::from("rgb(55, 55, 255)"));
+            if self.state == BuilderMode::Blocks || self.state == BuilderMode::Walls {
                 ctx.fill_rect(
-                    self.start.x,
-                    self.start.y, 
-                    self.end.x - self.start.x,
-                    self.end.y - self.start.y
+                    start.x,
+                    start.y,
+                    end.x - start.x,
+                    end.y - start.y
                 );
-                let _ = ctx.fill();
-                // log!("Block pos ", self.end.x - self.start.x, self.end.y - self.start.y);
-            }
-        } else if self.state == "CIRCLES" {
-            if self.mouse.mouse_down && self.end.x != 0.0 && self.end.y != 0.0 {
-                let rad = utils::dist_between_points(
-                    Point::new(self.start.x, self.start.y),
-                    Point::new(self.end.x, self.end.y)
-                );
-
-                ctx.set_fill_style(&JsValue::from("rgb(55, 55, 255)"));
+            } else if self.state == BuilderMode::Circles {
+                let rad = utils::dist_between_points(start, end);
                 let _ = ctx.begin_path();
                 let _ = ctx.arc(
-                    self.start.x,
-                    self.start.y,
-                    rad, 
-                    0.0, 
+                    start.x,
+                    start.y,
+                    rad,
+                    0.0,
                     std::f64::consts::PI * 2.0
                 );
                 let _ = ctx.fill();
-                // log!("Block pos ", self.end.x - self.start.x, self.end.y - self.start.y);
+            } else if self.state == BuilderMode::Bars {
+                let (length, angle) = bar_from_points(&start, &end);
+                let corners = DangerBar::new(start.x, start.y, length, BAR_THICKNESS, BAR_SPEED, angle).rect().corners();
+                ctx.begin_path();
+                ctx.move_to(corners[0].x, corners[0].y);
+                for corner in corners[1..].iter() {
+                    ctx.line_to(corner.x, corner.y);
+                }
+                ctx.close_path();
+                ctx.fill();
             }
         }
 
         self.player.render(&mut ctx);
         self.goal.render(&mut ctx);
 
+        if self.state == BuilderMode::Select {
+            self.render_selection(&mut ctx);
+        } else if self.state == BuilderMode::Images {
+            self.render_image_selection(&mut ctx);
+        } else if self.state == BuilderMode::Paths {
+            self.render_path_selection(&mut ctx);
+        } else if self.state == BuilderMode::Polygons {
+            self.render_polygon_draft(&mut ctx);
+        }
+        if self.mouse.mouse_down {
+            self.render_guides(&mut ctx);
+        }
+
+        self.check_scene();
+        if self.show_path {
+            self.render_path(&mut ctx);
+        }
+        if let Some(warning) = self.scene_warning.clone() {
+            ctx.set_font("32px arial");
+            utils::drop_shadow_string(&mut ctx, warning, 20.0, 40.0);
+        }
+
         window()
             .unwrap()
             .request_animation_frame(self.callback.as_ref().unchecked_ref())
             .unwrap();
     }
 
-    fn save_data(&self) -> String {
-        let mut ret = LevelModel::new();
+    // Start playing the scene as it is now, or go back to editing it
+    fn toggle_playtest(&mut self) {
+        if self.playtest.is_some() {
+            self.playtest = None;
+        } else {
+            self.playtest = Some(Simulation::new(&self.to_level_model()));
+            self.selected = None;
+            self.drag = None;
+            self.selected_image = None;
+            self.image_drag = None;
+        }
+        self.playtest_step = 0.0;
+        self.playtest_flash = 0.0;
+        self.mouse.mouse_down = false;
+        self.start = None;
+        self.end = None;
+    }
+
+    fn render_playtest(&mut self, ctx: &mut CanvasRenderingContext2d) {
+        let Some(sim) = self.playtest.as_mut() else {
+            return;
+        };
+        for wall in sim.walls.iter_mut() {
+            wall.render(ctx);
+        }
+        for block in sim.blocks.iter_mut() {
+            block.render(ctx);
+        }
+        for circle in sim.circles.iter_mut() {
+            circle.render(ctx);
+        }
+        for bar in sim.bars.iter_mut() {
+            bar.render(ctx);
+        }
+        for polygon in sim.polygons.iter_mut() {
+            polygon.render(ctx);
+        }
+        for image in self.images.iter_mut() {
+            image.render(ctx);
+        }
+        sim.goal.render(ctx);
+        sim.player.render(ctx);
+
+        ctx.set_font("64px arial");
+        utils::drop_shadow_string(ctx, format!("Time: {}", utils::format_time(sim.elapsed())), 20.0, 750.0);
+        ctx.set_font("32px arial");
+        utils::drop_shadow_string(ctx, format!("Resets: {}", sim.resets()), 1080.0, 750.0);
+        utils::drop_shadow_string(ctx, "PLAYTEST - T to edit".to_string(), 20.0, 40.0);
+        if sim.has_won() {
+            ctx.set_font("128px arial");
+            utils::drop_shadow_string(ctx, "GOAL".to_string(), 480.0, 440.0);
+        }
+
+        if self.playtest_flash > 0.0 {
+            let alpha = (self.playtest_flash / DEAD_TIME) * 0.5;
+            ctx.set_fill_style(&JsValue::from(format!("rgba(255, 0, 0, {})", alpha)));
+            ctx.fill_rect(0.0, 0.0, GAME_WIDTH, GAME_HEIGHT);
+        }
+    }
+
+    // Find the most serious problem with the level, and the shortest path
+    // through it once the validator is happy
+    fn check_scene(&mut self) {
+        if !self.scene_dirty {
+            return;
+        }
+        // Solving takes a while, so only do it every so often during a drag
+        if self.mouse.mouse_down && self.cur_time - self.checked_time < SOLVE_INTERVAL {
+            return;
+        }
+        self.scene_dirty = false;
+        self.checked_time = self.cur_time;
+        let level = self.to_level_model();
+        self.solution = None;
+        self.scene_warning = match validator::validate(&level).first() {
+            Some(issue) => Some(issue.to_string()),
+            None => match solver::solve(&level) {
+                Ok(solution) => {
+                    self.solution = Some(solution);
+                    None
+                },
+                Err(err) => Some(err.to_string())
+            }
+        };
+    }
+
+    fn render_path(&self, ctx: &mut CanvasRenderingContext2d) {
+        let Some(solution) = &self.solution else {
+            return;
+        };
+        ctx.set_stroke_style(&JsValue::from("rgb(0, 255, 255)"));
+        ctx.set_line_width(3.0);
+        ctx.begin_path();
+        for (i, pt) in solution.path.iter().enumerate() {
+            if i == 0 {
+                ctx.move_to(pt.x, pt.y);
+            } else {
+                ctx.line_to(pt.x, pt.y);
+            }
+        }
+        ctx.stroke();
+
+        // Ring the pinch point at the size of the space there
+        let gap = &solution.gap;
+        ctx.set_stroke_style(&JsValue::from("rgb(255, 165, 0)"));
+        ctx.begin_path();
+        let _ = ctx.arc(gap.at.x, gap.at.y, gap.clearance, 0.0, std::f64::consts::PI * 2.0);
+        ctx.stroke();
+        ctx.set_line_width(1.0);
+
+        ctx.set_font("24px arial");
+        let stats = format!(
+            "Path: {:.0}px  Tightest gap: {:.0}px (player is {:.0}px)",
+            solution.length,
+            gap.clearance * 2.0,
+            PLAYER_SIZE * 2.0
+        );
+        utils::drop_shadow_string(ctx, stats, 20.0, 75.0);
+        if solution.timed {
+            utils::drop_shadow_string(ctx, "Needs timing past moving hazards, not checked".to_string(), 20.0, 105.0);
+        }
+    }
+
+    fn delete_selected(&mut self) {
+        if self.state == BuilderMode::Images {
+            if let Some(index) = self.selected_image.filter(|i| *i < self.images.len()) {
+                let image = self.images.remove(index);
+                self.record(EditCommand::DeleteImage { index, image: Self::image_model(&image) });
+            }
+            self.selected_image = None;
+            self.image_drag = None;
+            return;
+        }
+        match self.selected {
+            Some(Selection::Block(i)) if i < self.blocks.len() => {
+                let block = self.blocks.remove(i).model();
+                self.record(EditCommand::DeleteBlock { index: i, block });
+            },
+            Some(Selection::Circle(i)) if i < self.circles.len() => {
+                let circle = self.circles.remove(i).model();
+                self.record(EditCommand::DeleteCircle { index: i, circle });
+            },
+            Some(Selection::Bar(i)) if i < self.bars.len() => {
+                let bar = self.bars.remove(i).model();
+                self.record(EditCommand::DeleteBar { index: i, bar });
+            },
+            Some(Selection::Polygon(i)) if i < self.polygons.len() => {
+                let polygon = self.polygons.remove(i).model();
+                self.record(EditCommand::DeletePolygon { index: i, polygon });
+            },
+            Some(Selection::Wall(i)) if i < self.walls.len() => {
+                let wall = self.walls.remove(i).model();
+                self.record(EditCommand::DeleteWall { index: i, wall });
+            },
+            _ => {}
+        }
+        self.selected = None;
+        self.drag = None;
+    }
+
+    // Pick a hazard to give a path to, then every click adds a point to it
+    // until Escape or another mode is chosen
+    fn pick_or_add_waypoint(&mut self, pt: Point<f64>) {
+        if self.selected.is_none() {
+            // Bars spin in place, and polygons and walls stay put, so none can be given a path
+            self.selected = pick_shape(&self.blocks, &self.circles, &[], &[], &[], None, &pt).map(|(selection, _)| selection);
+            return;
+        }
+        let pt = self.snap_point(pt, None);
+        self.edit_motion(|motion| Some(add_waypoint(motion, &pt)));
+    }
+
+    // Modes where a shape is drawn by dragging from one point to another
+    fn drags_out_shapes(&self) -> bool {
+        matches!(self.state, BuilderMode::Blocks | BuilderMode::Circles | BuilderMode::Bars | BuilderMode::Walls)
+    }
+
+    // Each click adds a corner. Clicking the first corner again closes the outline.
+    fn add_polygon_point(&mut self, pt: Point<f64>) {
+        if self.polygon_points.len() >= 3 && on_handle(&self.polygon_points[0], &pt) {
+            self.finish_polygon();
+            return;
+        }
+        let pt = self.snap_point(pt, None);
+        self.polygon_points.push(pt);
+    }
+
+    // Keeps drawing until there are enough corners to make a shape
+    fn finish_polygon(&mut self) {
+        if self.polygon_points.len() < 3 {
+            return;
+        }
+        let polygon = DangerPolygon::new(std::mem::take(&mut self.polygon_points));
+        self.record(EditCommand::AddPolygon(polygon.model()));
+        self.polygons.push(polygon);
+    }
+
+    // Change the selected shape's path, recording it if anything changed
+    fn edit_motion<F: FnOnce(Option<LevelMotionModel>) -> Option<LevelMotionModel>>(&mut self, change: F) {
+        let command = match self.selected {
+            Some(Selection::Block(index)) if index < self.blocks.len() => {
+                let before = self.blocks[index].anim.motion.clone();
+                let after = change(before.clone());
+                self.blocks[index].anim.motion = after.clone();
+                EditCommand::ChangeBlockMotion { index, before, after }
+            },
+            Some(Selection::Circle(index)) if index < self.circles.len() => {
+                let before = self.circles[index].anim.motion.clone();
+                let after = change(before.clone());
+                self.circles[index].anim.motion = after.clone();
+                EditCommand::ChangeCircleMotion { index, before, after }
+            },
+            _ => return
+        };
+        match &command {
+            EditCommand::ChangeBlockMotion { before, after, .. } | EditCommand::ChangeCircleMotion { before, after, .. } if before == after => {},
+            _ => self.record(command)
+        }
+    }
+
+    // Change when the selected shape switches on and off, recording it if anything changed
+    fn edit_pulse<F: FnOnce(Option<LevelPulseModel>) -> Option<LevelPulseModel>>(&mut self, change: F) {
+        let command = match self.selected {
+            Some(Selection::Block(index)) if index < self.blocks.len() => {
+                let before = self.blocks[index].anim.pulse;
+                let after = change(before);
+                self.blocks[index].anim.pulse = after;
+                EditCommand::ChangeBlockPulse { index, before, after }
+            },
+            Some(Selection::Circle(index)) if index < self.circles.len() => {
+                let before = self.circles[index].anim.pulse;
+                let after = change(before);
+                self.circles[index].anim.pulse = after;
+                EditCommand::ChangeCirclePulse { index, before, after }
+            },
+            _ => return
+        };
+        match &command {
+            EditCommand::ChangeBlockPulse { before, after, .. } | EditCommand::ChangeCirclePulse { before, after, .. } if before == after => {},
+            _ => self.record(command)
+        }
+    }
+
+    // Change the selected bar, recording it if anything changed
+    fn edit_bar<F: FnOnce(LevelBarModel) -> LevelBarModel>(&mut self, change: F) {
+        let Some(Selection::Bar(index)) = self.selected else {
+            return;
+        };
+        let Some(bar) = self.bars.get_mut(index) else {
+            return;
+        };
+        let before = bar.model();
+        let after = change(before.clone());
+        if after != before {
+            *bar = DangerBar::from_model(&after);
+            self.record(EditCommand::ChangeBar { index, before, after });
+        }
+    }
+
+    // Faint lines for every path, so they show in every mode
+    fn render_motions(&self, ctx: &mut CanvasRenderingContext2d) {
+        ctx.set_stroke_style(&JsValue::from("rgba(255, 255, 255, 0.4)"));
+        for block in self.blocks.iter() {
+            if let Some(motion) = &block.anim.motion {
+                Self::render_motion(ctx, motion, block.centre());
+            }
+        }
+        for circle in self.circles.iter() {
+            if let Some(motion) = &circle.anim.motion {
+                Self::render_motion(ctx, motion, circle.pos.loc);
+            }
+        }
+    }
+
+    fn render_motion(ctx: &mut CanvasRenderingContext2d, motion: &LevelMotionModel, home: Point<f64>) {
+        ctx.begin_path();
+        match (motion.kind, motion.points.first()) {
+            (LevelMotionKind::Orbit, Some(centre)) => {
+                let radius = utils::dist_between_points(home, Point::new(centre.x, centre.y));
+                let _ = ctx.arc(centre.x, centre.y, radius, 0.0, std::f64::consts::PI * 2.0);
+            },
+            _ => {
+                ctx.move_to(home.x, home.y);
+                for pt in motion.points.iter() {
+                    ctx.line_to(pt.x, pt.y);
+                }
+                if motion.kind == LevelMotionKind::Loop {
+                    ctx.close_path();
+                }
+            }
+        }
+        ctx.stroke();
+        for pt in motion.points.iter() {
+            ctx.stroke_rect(pt.x - 3.0, pt.y - 3.0, 6.0, 6.0);
+        }
+    }
+
+    // Outline the chosen shape where it is now, and where its path takes it
+    fn render_path_selection(&self, ctx: &mut CanvasRenderingContext2d) {
+        ctx.set_font("24px arial");
+        ctx.set_stroke_style(&JsValue::from("rgb(255, 255, 255)"));
+        let motion = match self.selected {
+            Some(Selection::Block(i)) if i < self.blocks.len() => {
+                let block = &self.blocks[i];
+                ctx.stroke_rect(block.pos.loc.x, block.pos.loc.y, block.pos.width, block.pos.height);
+                if let Some(motion) = &block.anim.motion {
+                    let offset = motion::offset_at(motion, block.centre(), self.cur_time);
+                    ctx.stroke_rect(block.pos.loc.x + offset.x, block.pos.loc.y + offset.y, block.pos.width, block.pos.height);
+                }
+                block.anim.motion.clone()
+            },
+            Some(Selection::Circle(i)) if i < self.circles.len() => {
+                let pos = &self.circles[i].pos;
+                ctx.begin_path();
+                let _ = ctx.arc(pos.loc.x, pos.loc.y, pos.size, 0.0, std::f64::consts::PI * 2.0);
+                ctx.stroke();
+                if let Some(motion) = &self.circles[i].anim.motion {
+                    let offset = motion::offset_at(motion, pos.loc, self.cur_time);
+                    ctx.begin_path();
+                    let _ = ctx.arc(pos.loc.x + offset.x, pos.loc.y + offset.y, pos.size, 0.0, std::f64::consts::PI * 2.0);
+                    ctx.stroke();
+                }
+                self.circles[i].anim.motion.clone()
+            },
+            _ => {
+                utils::drop_shadow_string(ctx, "Click a hazard to give it a path".to_string(), 20.0, 715.0);
+                return;
+            }
+        };
+        let status = match motion {
+            Some(motion) => format!(
+                "{} {:.0}px/s phase {:.3} - L type, -/= speed, ,/. phase",
+                motion_label(motion.kind),
+                motion.speed,
+                motion.phase
+            ),
+            None => "Click to add points, Esc when done".to_string()
+        };
+        utils::drop_shadow_string(ctx, status, 20.0, 715.0);
+    }
+
+    fn image_model(image: &Image) -> LevelImageModel {
+        LevelImageModel {
+            filename: image.filename().to_string(),
+            x: image.loc.x,
+            y: image.loc.y
+        }
+    }
+
+    // Grab the topmost image under the pointer, or place a new one there
+    fn pick_or_place_image(&mut self, pt: Point<f64>) {
+        match (0..self.images.len()).rev().find(|i| self.images[*i].point_inside(pt.x, pt.y)) {
+            Some(index) => {
+                let loc = self.images[index].loc;
+                self.selected_image = Some(index);
+                self.image_drag = Some((Point::new(pt.x - loc.x, pt.y - loc.y), loc));
+            },
+            None => {
+                let loc = self.snap_point(pt, None);
+                let filename = format!("{}{}", IMAGE_DIR, BUILDER_IMAGES[self.image_choice]);
+                let image = Image::new(filename, loc.x, loc.y);
+                self.record(EditCommand::AddImage(Self::image_model(&image)));
+                self.images.push(image);
+                self.selected_image = Some(self.images.len() - 1);
+                self.image_drag = None;
+            }
+        }
+    }
+
+    fn finish_image_drag(&mut self) {
+        if let (Some(index), Some((_, before))) = (self.selected_image, self.image_drag.take()) {
+            if let Some(image) = self.images.get(index).filter(|image| image.loc != before) {
+                self.record(EditCommand::MoveImage { index, before, after: image.loc });
+            }
+        }
+    }
+
+    fn render_image_selection(&self, ctx: &mut CanvasRenderingContext2d) {
+        ctx.set_font("24px arial");
+        let choice = format!("[ ] to change image: {}", BUILDER_IMAGES[self.image_choice]);
+        utils::drop_shadow_string(ctx, choice, 20.0, 715.0);
+
+        if let Some(image) = self.selected_image.and_then(|i| self.images.get(i)) {
+            let bounds = image.bounds();
+            ctx.set_stroke_style(&JsValue::from("rgb(255, 255, 255)"));
+            ctx.stroke_rect(bounds.loc.x, bounds.loc.y, bounds.width, bounds.height);
+        }
+    }
+
+    // Snap against the grid and the edges of every block but the one being moved
+    fn snap_point(&mut self, pt: Point<f64>, exclude: Option<usize>) -> Point<f64> {
+        let rects: Vec::<Rect<f64>> = self.blocks.iter()
+            .enumerate()
+            .filter(|(i, _)| Some(*i) != exclude)
+            .map(|(_, b)| b.pos)
+            .collect();
+        let snapped = self.snap.snap(pt, &rects);
+        self.last_snap = Some(snapped);
+        snapped.point
+    }
+
+    fn snap_status(&self) -> String {
+        let grid = if self.snap.grid {
+            format!("GRID {}", self.snap.grid_size())
+        } else {
+            "GRID OFF".to_string()
+        };
+        let guides = if self.snap.guides { "GUIDES" } else { "NO GUIDES" };
+        let markers = if self.snap.markers { " | SNAP P/G" } else { "" };
+        format!("{} | {}{}", grid, guides, markers)
+    }
+
+    fn render_grid(&self, ctx: &mut CanvasRenderingContext2d) {
+        let size = self.snap.grid_size();
+        ctx.set_stroke_style(&JsValue::from("rgba(255, 255, 255, 0.08)"));
+        ctx.begin_path();
+        let mut x = size;
+        while x < GAME_WIDTH {
+            ctx.move_to(x, 0.0);
+            ctx.line_to(x, GAME_HEIGHT);
+            x += size;
+        }
+        let mut y = size;
+        while y < GAME_HEIGHT {
+            ctx.move_to(0.0, y);
+            ctx.line_to(GAME_WIDTH, y);
+            y += size;
+        }
+        ctx.stroke();
+    }
+
+    fn render_guides(&self, ctx: &mut CanvasRenderingContext2d) {
+        let Some(snapped) = self.last_snap else {
+            return;
+        };
+        ctx.set_stroke_style(&JsValue::from("rgb(255, 0, 255)"));
+        ctx.begin_path();
+        if let Some(x) = snapped.guide_x {
+            ctx.move_to(x, 0.0);
+            ctx.line_to(x, GAME_HEIGHT);
+        }
+        if let Some(y) = snapped.guide_y {
+            ctx.move_to(0.0, y);
+            ctx.line_to(GAME_WIDTH, y);
+        }
+        ctx.stroke();
+    }
+
+    // An edit with the shape as it is now for both before and after
+    fn shape_change(&self, selection: Selection) -> Option<EditCommand> {
+        match selection {
+            Selection::Block(index) => self.blocks.get(index).map(|b| {
+                EditCommand::ChangeBlock { index, before: b.model(), after: b.model() }
+            }),
+            Selection::Circle(index) => self.circles.get(index).map(|c| {
+                EditCommand::ChangeCircle { index, before: c.model(), after: c.model() }
+            }),
+            Selection::Bar(index) => self.bars.get(index).map(|b| {
+                EditCommand::ChangeBar { index, before: b.model(), after: b.model() }
+            }),
+            Selection::Polygon(index) => self.polygons.get(index).map(|p| {
+                EditCommand::ChangePolygon { index, before: p.model(), after: p.model() }
+            }),
+            Selection::Wall(index) => self.walls.get(index).map(|w| {
+                EditCommand::ChangeWall { index, before: w.model(), after: w.model() }
+            })
+        }
+    }
+
+    fn finish_drag(&mut self) {
+        let change = match self.drag_change.take() {
+            Some(EditCommand::ChangeBlock { index, before, .. }) => self.blocks.get(index)
+                .map(|b| b.model())
+                .filter(|after| *after != before)
+                .map(|after| EditCommand::ChangeBlock { index, before, after }),
+            Some(EditCommand::ChangeCircle { index, before, .. }) => self.circles.get(index)
+                .map(|c| c.model())
+                .filter(|after| *after != before)
+                .map(|after| EditCommand::ChangeCircle { index, before, after }),
+            Some(EditCommand::ChangeBar { index, before, .. }) => self.bars.get(index)
+                .map(|b| b.model())
+                .filter(|after| *after != before)
+                .map(|after| EditCommand::ChangeBar { index, before, after }),
+            Some(EditCommand::ChangePolygon { index, before, .. }) => self.polygons.get(index)
+                .map(|p| p.model())
+                .filter(|after| *after != before)
+                .map(|after| EditCommand::ChangePolygon { index, before, after }),
+            Some(EditCommand::ChangeWall { index, before, .. }) => self.walls.get(index)
+                .map(|w| w.model())
+                .filter(|after| *after != before)
+                .map(|after| EditCommand::ChangeWall { index, before, after }),
+            _ => None
+        };
+        if let Some(change) = change {
+            self.record(change);
+        }
+    }
+
+    fn step_history(&mut self, redo: bool) {
+        let mut target = EditTarget {
+            blocks: &mut self.blocks,
+            circles: &mut self.circles,
+            bars: &mut self.bars,
+            polygons: &mut self.polygons,
+            walls: &mut self.walls,
+            images: &mut self.images,
+            player: &mut self.player.loc,
+            goal: &mut self.goal.circle.loc
+        };
+        if redo {
+            self.history.redo(&mut target);
+        } else {
+            self.history.undo(&mut target);
+        }
+        self.scene_dirty = true;
+        // Indexes may no longer point at the same shape
+        self.selected = None;
+        self.selected_image = None;
+        self.image_drag = None;
+        self.drag = None;
+        self.drag_change = None;
+    }
+
+    // Outline the selected shape and draw its resize handles
+    fn render_selection(&self, ctx: &mut CanvasRenderingContext2d) {
+        ctx.set_stroke_style(&JsValue::from("rgb(255, 255, 255)"));
+        ctx.set_fill_style(&JsValue::from("rgb(255, 255, 255)"));
+        ctx.set_font("24px arial");
+        let handles = match self.selected {
+            Some(Selection::Block(i)) if i < self.blocks.len() => {
+                let pos = &self.blocks[i].pos;
+                ctx.stroke_rect(pos.loc.x, pos.loc.y, pos.width, pos.height);
+                if let Some(pulse) = &self.blocks[i].anim.pulse {
+                    utils::drop_shadow_string(ctx, pulse_label(pulse), 20.0, 715.0);
+                }
+                block_corners(pos).to_vec()
+            },
+            Some(Selection::Circle(i)) if i < self.circles.len() => {
+                let pos = &self.circles[i].pos;
+                ctx.begin_path();
+                let _ = ctx.arc(pos.loc.x, pos.loc.y, pos.size, 0.0, std::f64::consts::PI * 2.0);
+                ctx.stroke();
+                if let Some(pulse) = &self.circles[i].anim.pulse {
+                    utils::drop_shadow_string(ctx, pulse_label(pulse), 20.0, 715.0);
+                }
+                vec![radius_handle(pos)]
+            },
+            Some(Selection::Bar(i)) if i < self.bars.len() => {
+                let corners = self.bars[i].start_rect().corners();
+                ctx.begin_path();
+                ctx.move_to(corners[0].x, corners[0].y);
+                for corner in corners[1..].iter() {
+                    ctx.line_to(corner.x, corner.y);
+                }
+                ctx.close_path();
+                ctx.stroke();
+                let bar = &self.bars[i];
+                let status = format!("Spin {:.0} deg/s, thickness {:.0} - -/= spin, ,/. thickness", bar.speed, bar.thickness);
+                utils::drop_shadow_string(ctx, status, 20.0, 715.0);
+                vec![bar_tip(bar)]
+            },
+            Some(Selection::Polygon(i)) if i < self.polygons.len() => {
+                let points = &self.polygons[i].points;
+                ctx.begin_path();
+                ctx.move_to(points[0].x, points[0].y);
+                for point in points[1..].iter() {
+                    ctx.line_to(point.x, point.y);
+                }
+                ctx.close_path();
+                ctx.stroke();
+                points.clone()
+            },
+            Some(Selection::Wall(i)) if i < self.walls.len() => {
+                let pos = &self.walls[i].pos;
+                ctx.stroke_rect(pos.loc.x, pos.loc.y, pos.width, pos.height);
+                block_corners(pos).to_vec()
+            },
+            _ => Vec::new()
+        };
+        for handle in handles.iter() {
+            ctx.fill_rect(handle.x - HANDLE_SIZE / 2.0, handle.y - HANDLE_SIZE / 2.0, HANDLE_SIZE, HANDLE_SIZE);
+        }
+    }
+
+    // The corners placed so far, with a line on to the pointer for the next one
+    fn render_polygon_draft(&self, ctx: &mut CanvasRenderingContext2d) {
+        ctx.set_font("24px arial");
+        let Some(first) = self.polygon_points.first() else {
+            utils::drop_shadow_string(ctx, "Click to place the first corner".to_string(), 20.0, 715.0);
+            return;
+        };
+        ctx.set_stroke_style(&JsValue::from("rgb(55, 55, 255)"));
+        ctx.set_line_width(3.0);
+        ctx.begin_path();
+        ctx.move_to(first.x, first.y);
+        for point in self.polygon_points[1..].iter() {
+            ctx.line_to(point.x, point.y);
+        }
+        ctx.line_to(self.mouse.loc.x, self.mouse.loc.y);
+        ctx.stroke();
+        ctx.set_line_width(1.0);
+
+        ctx.set_fill_style(&JsValue::from("rgb(255, 255, 255)"));
+        for point in self.polygon_points.iter() {
+            ctx.fill_rect(point.x - HANDLE_SIZE / 2.0, point.y - HANDLE_SIZE / 2.0, HANDLE_SIZE, HANDLE_SIZE);
+        }
+        let status = if self.polygon_points.len() >= 3 {
+            "Click the first corner or Enter to finish, Delete removes a corner"
+        } else {
+            "Click to add corners, Delete removes one"
+        };
+        utils::drop_shadow_string(ctx, status.to_string(), 20.0, 715.0);
+    }
+
+    fn load_level(&mut self, level: LevelModel) {
+        self.player = Player::new(level.player.x, level.player.y);
+        self.goal = Goal::new(level.goal.x, level.goal.y);
+
+        // Never updated while editing, so moving shapes stay where they were placed
+        self.blocks = level.danger_blocks.iter().map(DangerBlock::from_model).collect();
+        self.circles = level.danger_circles.iter().map(DangerCircle::from_model).collect();
+        self.bars = level.danger_bars.iter().map(DangerBar::from_model).collect();
+        self.polygons = level.danger_polygons.iter().map(DangerPolygon::from_model).collect();
+        self.polygon_points.clear();
+        self.walls = level.walls.iter().map(Wall::from_model).collect();
+
+        let mut images = Vec::<Image>::new();
+        for i in level.images.iter() {
+            images.push(Image::new(i.filename.clone(), i.x, i.y));
+        }
+        self.images = images;
+
+        self.level = level;
+        self.selected = None;
+        self.selected_image = None;
+        self.image_drag = None;
+        self.drag = None;
+        self.drag_change = None;
+        self.history.clear();
+        self.scene_dirty = true;
+        self.start = None;
+        self.end = None;
+    }
+
+    // Every edit goes through here so the scene gets checked again
+    fn record(&mut self, command: EditCommand) {
+        self.history.record(command);
+        self.scene_dirty = true;
+    }
+
+    fn to_level_model(&self) -> LevelModel {
+        // Start from the loaded level so anything the builder doesn't edit is kept
+        let mut ret = self.level.clone();
         ret.player.x = self.player.loc.x;
         ret.player.y = self.player.loc.y;
 
         ret.goal.x = self.goal.circle.loc.x;
         ret.goal.y = self.goal.circle.loc.y;
 
-        for b in self.blocks.iter() {
-            ret.danger_blocks.push(
-                LevelBlockModel::new(
-                    b.pos.loc.x,
-                    b.pos.loc.y,
-                    b.pos.width,
-                    b.pos.height
-                )
-            );
-        }
-
-        for c in self.circles.iter() {
-            ret.danger_circles.push(
-                LevelCircleModel::new(
-                    c.pos.loc.x,
-                    c.pos.loc.y,
-                    c.pos.size
-                )
-            );
-        }
-        let ret_str = serde_json::to_string(&ret).unwrap();
+        ret.danger_blocks = self.blocks.iter().map(|b| b.model()).collect();
+        ret.danger_circles = self.circles.iter().map(|c| c.model()).collect();
+        ret.danger_bars = self.bars.iter().map(|b| b.model()).collect();
+        ret.danger_polygons = self.polygons.iter().map(|p| p.model()).collect();
+        ret.walls = self.walls.iter().map(|w| w.model()).collect();
+
+        ret.images = self.images.iter().map(Self::image_model).collect();
+        ret
+    }
+
+    fn save_data(&self) -> String {
+        let ret_str = serde_json::to_string_pretty(&self.to_level_model()).unwrap();
         log!(ret_str.clone());
         ret_str
     }
 
-    fn get_block_list(&self) -> Html {
+    // Hand the level to the browser as a file download
+    fn download_level(&self) -> Result<(), JsValue> {
+        let parts = js_sys::Array::of1(&JsValue::from_str(&self.json_text));
+        let options = BlobPropertyBag::new();
+        options.set_type("application/json");
+        let blob = Blob::new_with_str_sequence_and_options(&parts, &options)?;
+        let url = Url::create_object_url_with_blob(&blob)?;
+
+        let window = window().ok_or("no window")?;
+        let document = window.document().ok_or("no document")?;
+        let body = document.body().ok_or("no body")?;
+        let anchor: HtmlAnchorElement = document.create_element("a")?.unchecked_into();
+        anchor.set_href(&url);
+        anchor.set_download(&download_name(&self.level_file));
+        // Firefox ignores clicks on a link that isn't in the page
+        body.append_child(&anchor)?;
+        anchor.click();
+        anchor.remove();
+
+        let revoke = Closure::once_into_js(move || {
+            let _ = Url::revoke_object_url(&url);
+        });
+        window.set_timeout_with_callback_and_timeout_and_arguments_0(revoke.unchecked_ref(), DOWNLOAD_REVOKE_DELAY)?;
+        Ok(())
+    }
+
+    fn get_level_io(&self, ctx: &Context<Self>) -> Html {
+        let onselect = ctx.link().callback(move |evt: Event| {
+            LevelBuildMsg::SelectFile(evt.target_unchecked_into::<HtmlSelectElement>().value())
+        });
+        let onopen = ctx.link().callback(move |_evt: MouseEvent| {
+            LevelBuildMsg::OpenLevel
+        });
+        let onedit = ctx.link().callback(move |evt: InputEvent| {
+            LevelBuildMsg::JsonEdited(evt.target_unchecked_into::<HtmlTextAreaElement>().value())
+        });
+        let onload = ctx.link().callback(move |_evt: MouseEvent| {
+            LevelBuildMsg::LoadJson
+        });
+        let onrename = ctx.link().callback(move |evt: InputEvent| {
+            LevelBuildMsg::FileNameEdited(evt.target_unchecked_into::<HtmlInputElement>().value())
+        });
+        let ondownload = ctx.link().callback(move |_evt: MouseEvent| {
+            LevelBuildMsg::DownloadLevel
+        });
+        let onupload = ctx.link().callback(move |evt: Event| {
+            let input = evt.target_unchecked_into::<HtmlInputElement>();
+            let file = input.files().and_then(|files| files.get(0));
+            // Clear it so picking the same file again still fires a change
+            input.set_value("");
+            match file {
+                Some(file) => LevelBuildMsg::UploadLevel(file),
+                None => LevelBuildMsg::Null
+            }
+        });
+
+        let entries = match &self.manifest {
+            Some(manifest) => (0..manifest.len()).filter_map(|i| manifest.entry(i).cloned()).collect(),
+            None => Vec::new()
+        };
+
         html!{
-            <h3 class={"display_area"}>{self.save_data()}</h3>            
+            <div class={"display_area"}>
+                <h3>{"Open a level"}</h3>
+                <select onchange={onselect}>
+                    { for entries.iter().map(|entry| html!{
+                        <option value={entry.file.clone()} selected={entry.file == self.selected_file}>
+                            {format!("{} ({})", entry.title, entry.file)}
+                        </option>
+                    }) }
+                </select>
+                <button onclick={onopen}>{"Open"}</button>
+                <h3>{"Open a file from your computer"}</h3>
+                <input type="file" accept=".json,application/json" onchange={onupload}/>
+                <h3>{"Save"}</h3>
+                <input type="text" value={self.level_file.clone()} oninput={onrename}/>
+                <button onclick={ondownload}>{"Download"}</button>
+                <p>{"Or edit the JSON directly and load it back in."}</p>
+                <textarea rows="24" value={self.json_text.clone()} oninput={onedit}></textarea><br/>
+                <button onclick={onload}>{"Load JSON"}</button>
+                if let Some(message) = &self.io_message {
+                    <p>{message}</p>
+                }
+            </div>
         }
     }
 
@@ -431,8 +1897,215 @@ impl LevelBuilder {
                 <h5>{"C - Circles"}</h5>
                 <h5>{"G - Goal"}</h5>
                 <h5>{"P - Player"}</h5>
+                <h5>{"V - Select, drag to move, drag a handle to resize or turn a bar"}</h5>
+                <h5>{"I - Images, click to place or drag to move"}</h5>
+                <h5>{"[ and ] - Change which image is placed"}</h5>
+                <h5>{"R - Bars, drag from the pivot out to one end"}</h5>
+                <h5>{"- and = - Change the spin of the selected bar, , and . its thickness"}</h5>
+                <h5>{"X - Walls, safe to touch but the player can't be dragged through them"}</h5>
+                <h5>{"F - Polygons, click each corner then the first again or Enter to finish"}</h5>
+                <h5>{"W - Paths, click a hazard then click to add points it moves through"}</h5>
+                <h5>{"L - Change the path between back and forth, loop and orbit"}</h5>
+                <h5>{"- and = - Slow down or speed up the selected path"}</h5>
+                <h5>{", and . - Change where along its path the hazard starts"}</h5>
+                <h5>{"U - Make the selected hazard blink, breathe, or stay on"}</h5>
+                <h5>{"Esc - Finish the path, or drop the selection"}</h5>
+                <h5>{"Delete / Backspace - Remove the selected shape or image, or the last path point"}</h5>
+                <h5>{"Ctrl+Z - Undo"}</h5>
+                <h5>{"Ctrl+Shift+Z - Redo"}</h5>
+                <h5>{"T - Playtest the level, T again to go back to editing"}</h5>
+                <h5>{"O - Toggle the shortest safe path overlay"}</h5>
+                <h5>{"N - Toggle the snap grid"}</h5>
+                <h5>{"M - Change the grid size (10, 20, 40)"}</h5>
+                <h5>{"H - Toggle guides to the edges and centres of blocks"}</h5>
+                <h5>{"K - Toggle snapping the player and goal"}</h5>
                 <h5>{"Q - Reset All"}</h5>
+                <h5>{"S - Save to JSON"}</h5>
             </h3>
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::levels::level_model::LevelWallModel;
+
+    fn shapes() -> (Vec::<DangerBlock>, Vec::<DangerCircle>) {
+        (
+            vec![DangerBlock::new(100.0, 100.0, 200.0, 100.0)],
+            vec![DangerCircle::new(280.0, 180.0, 40.0)]
+        )
+    }
+
+    #[test]
+    fn pick_topmost_shape() {
+        let (blocks, circles) = shapes();
+        let picked = pick_shape(&blocks, &circles, &[], &[], &[], None, &Point::new(150.0, 150.0));
+        assert_eq!(picked, Some((Selection::Block(0), SelectDrag::Move(Point::new(50.0, 50.0)))));
+
+        // The circle is drawn over the block where they overlap
+        let picked = pick_shape(&blocks, &circles, &[], &[], &[], None, &Point::new(270.0, 170.0));
+        assert_eq!(picked.map(|p| p.0), Some(Selection::Circle(0)));
+
+        assert_eq!(pick_shape(&blocks, &circles, &[], &[], &[], None, &Point::new(600.0, 600.0)), None);
+    }
+
+    #[test]
+    fn resize_from_a_corner() {
+        let (mut blocks, mut circles) = shapes();
+        let selected = Some(Selection::Block(0));
+        let (selection, drag) = pick_shape(&blocks, &circles, &[], &[], &[], selected, &Point::new(302.0, 98.0)).unwrap();
+        assert_eq!(drag, SelectDrag::Resize(Point::new(100.0, 200.0)));
+
+        // Dragging past the opposite corner flips the block rather than making it negative
+        drag_shape(&mut blocks, &mut circles, &mut [], &mut [], &mut [], (selection, drag), &Point::new(50.0, 400.0));
+        assert_eq!(blocks[0].pos, Rect::new(50.0, 200.0, 50.0, 200.0));
+    }
+
+    #[test]
+    fn move_and_resize_circle() {
+        let (mut blocks, mut circles) = shapes();
+        let (selection, drag) = pick_shape(&blocks, &circles, &[], &[], &[], Some(Selection::Circle(0)), &Point::new(320.0, 180.0)).unwrap();
+        assert_eq!(drag, SelectDrag::Radius);
+        drag_shape(&mut blocks, &mut circles, &mut [], &mut [], &mut [], (selection, drag), &Point::new(280.0, 240.0));
+        assert_eq!(circles[0].pos.size, 60.0);
+
+        drag_shape(&mut blocks, &mut circles, &mut [], &mut [], &mut [], (selection, SelectDrag::Move(Point::new(10.0, 0.0))), &Point::new(510.0, 500.0));
+        assert_eq!(circles[0].pos.loc, Point::new(500.0, 500.0));
+    }
+
+    #[test]
+    fn paths_move_with_their_shape() {
+        let (mut blocks, mut circles) = shapes();
+        blocks[0].anim.motion = Some(add_waypoint(None, &Point::new(400.0, 100.0)));
+        let mut orbit = add_waypoint(None, &Point::new(300.0, 300.0));
+        orbit.kind = LevelMotionKind::Orbit;
+        circles[0].anim.motion = Some(orbit);
+        let start = blocks[0].pos.loc;
+
+        drag_shape(&mut blocks, &mut circles, &mut [], &mut [], &mut [], (Selection::Block(0), SelectDrag::Move(Point::new(0.0, 0.0))), &Point::new(start.x + 50.0, start.y + 20.0));
+        assert_eq!(blocks[0].anim.motion.as_ref().unwrap().points[0], LevelPointModel { x: 450.0, y: 120.0 });
+
+        let start = circles[0].pos.loc;
+        drag_shape(&mut blocks, &mut circles, &mut [], &mut [], &mut [], (Selection::Circle(0), SelectDrag::Move(Point::new(0.0, 0.0))), &Point::new(start.x - 100.0, start.y));
+        assert_eq!(circles[0].anim.motion.as_ref().unwrap().points[0], LevelPointModel { x: 200.0, y: 300.0 });
+    }
+
+    #[test]
+    fn download_names() {
+        assert_eq!(download_name("level9"), "level9.json");
+        assert_eq!(download_name(" level9.json "), "level9.json");
+        assert_eq!(download_name("assets/levels/level2.json"), "level2.json");
+        assert_eq!(download_name(""), "level.json");
+    }
+
+    #[test]
+    fn image_list_matches_assets() {
+        let dir = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("assets/img");
+        let mut files: Vec::<String> = std::fs::read_dir(dir).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .filter(|name| name.ends_with(".png"))
+            .collect();
+        files.sort();
+        assert_eq!(files, BUILDER_IMAGES.to_vec());
+    }
+
+    #[test]
+    fn select_blocks_with_negative_size() {
+        let blocks = vec![DangerBlock::new(300.0, 300.0, -100.0, -50.0)];
+        let picked = pick_shape(&blocks, &[], &[], &[], &[], None, &Point::new(250.0, 270.0));
+        assert_eq!(picked.map(|p| p.0), Some(Selection::Block(0)));
+    }
+
+    #[test]
+    fn draw_and_undraw_a_path() {
+        let motion = add_waypoint(None, &Point::new(200.0, 100.0));
+        assert_eq!(motion.kind, LevelMotionKind::Linear);
+        let motion = add_waypoint(Some(motion), &Point::new(200.0, 300.0));
+        assert_eq!(motion.points.len(), 2);
+
+        // An orbit moves its centre rather than growing
+        let orbit = LevelMotionModel { kind: next_motion_kind(next_motion_kind(motion.kind)), ..motion.clone() };
+        assert_eq!(orbit.kind, LevelMotionKind::Orbit);
+        assert_eq!(add_waypoint(Some(orbit), &Point::new(50.0, 50.0)).points, vec![LevelPointModel { x: 50.0, y: 50.0 }]);
+
+        let motion = remove_waypoint(Some(motion));
+        assert_eq!(motion.as_ref().map(|m| m.points.len()), Some(1));
+        assert_eq!(remove_waypoint(motion), None);
+    }
+
+    #[test]
+    fn move_and_turn_a_bar() {
+        let (blocks, circles) = shapes();
+        let mut bars = vec![DangerBar::new(600.0, 400.0, 200.0, 20.0, 90.0, 0.0)];
+        // Over the end of the bar, not just its bounding box
+        let picked = pick_shape(&blocks, &circles, &bars, &[], &[], None, &Point::new(690.0, 405.0));
+        assert_eq!(picked, Some((Selection::Bar(0), SelectDrag::Move(Point::new(90.0, 5.0)))));
+
+        let (selection, drag) = pick_shape(&blocks, &circles, &bars, &[], &[], Some(Selection::Bar(0)), &Point::new(702.0, 398.0)).unwrap();
+        assert_eq!(drag, SelectDrag::Turn);
+        drag_shape(&mut [], &mut [], &mut bars, &mut [], &mut [], (selection, drag), &Point::new(600.0, 550.0));
+        assert_eq!(bars[0].model(), LevelBarModel::new(600.0, 400.0, 300.0, 20.0, 90.0, 90.0));
+        assert!(bars[0].point_inside(600.0, 540.0));
+    }
+
+    #[test]
+    fn cycle_pulses() {
+        let blink = next_pulse(None).unwrap();
+        assert_eq!(blink.grow, 0.0);
+        let breathe = next_pulse(Some(blink)).unwrap();
+        assert_eq!((breathe.on, breathe.off, breathe.grow), (PULSE_ON, PULSE_OFF, PULSE_GROW));
+        assert_eq!(next_pulse(Some(breathe)), None);
+    }
+
+    #[test]
+    fn move_and_reshape_a_polygon() {
+        let (blocks, circles) = shapes();
+        let mut polygons = vec![DangerPolygon::new(vec![Point::new(600.0, 400.0), Point::new(800.0, 400.0), Point::new(600.0, 600.0)])];
+        // Inside the bounds but past the slanted edge
+        assert_eq!(pick_shape(&blocks, &circles, &[], &polygons, &[], None, &Point::new(750.0, 550.0)), None);
+        let (selection, drag) = pick_shape(&blocks, &circles, &[], &polygons, &[], None, &Point::new(650.0, 450.0)).unwrap();
+        assert_eq!((selection, drag), (Selection::Polygon(0), SelectDrag::Move(Point::new(50.0, 50.0))));
+        drag_shape(&mut [], &mut [], &mut [], &mut polygons, &mut [], (selection, drag), &Point::new(150.0, 150.0));
+        assert_eq!(polygons[0].points[2], Point::new(100.0, 300.0));
+
+        let (selection, drag) = pick_shape(&blocks, &circles, &[], &polygons, &[], Some(selection), &Point::new(302.0, 101.0)).unwrap();
+        assert_eq!(drag, SelectDrag::Corner(1));
+        drag_shape(&mut [], &mut [], &mut [], &mut polygons, &mut [], (selection, drag), &Point::new(300.0, 300.0));
+        assert!(polygons[0].point_inside(150.0, 250.0));
+        assert!(!polygons[0].point_inside(250.0, 120.0));
+    }
+
+    #[test]
+    fn walls_are_picked_under_hazards() {
+        let (mut blocks, circles) = shapes();
+        let mut walls = vec![Wall::new(0.0, 0.0, 400.0, 40.0), Wall::new(150.0, 300.0, -50.0, -250.0)];
+        // Normalised like a block, and under the block where they overlap
+        assert_eq!(walls[1].pos, Rect::new(100.0, 50.0, 50.0, 250.0));
+        let picked = pick_shape(&blocks, &circles, &[], &[], &walls, None, &Point::new(120.0, 120.0));
+        assert_eq!(picked.map(|p| p.0), Some(Selection::Block(0)));
+        let (selection, drag) = pick_shape(&blocks, &circles, &[], &[], &walls, None, &Point::new(120.0, 250.0)).unwrap();
+        assert_eq!(selection, Selection::Wall(1));
+
+        drag_shape(&mut blocks, &mut [], &mut [], &mut [], &mut walls, (selection, drag), &Point::new(520.0, 400.0));
+        assert_eq!(walls[1].pos, Rect::new(500.0, 200.0, 50.0, 250.0));
+        let (selection, drag) = pick_shape(&blocks, &circles, &[], &[], &walls, Some(selection), &Point::new(550.0, 450.0)).unwrap();
+        assert_eq!(drag, SelectDrag::Resize(Point::new(500.0, 200.0)));
+        drag_shape(&mut blocks, &mut [], &mut [], &mut [], &mut walls, (selection, drag), &Point::new(600.0, 300.0));
+        assert_eq!(walls[1].pos, Rect::new(500.0, 200.0, 100.0, 100.0));
+        assert_eq!(blocks[0].pos, Rect::new(100.0, 100.0, 200.0, 100.0));
+    }
+
+    #[test]
+    fn shapes_drawn_from_the_corner() {
+        // Snapping puts the start right on the origin
+        let drawn = drawn_shape(BuilderMode::Blocks, &Point::new(0.0, 0.0), &Point::new(40.0, 200.0));
+        assert_eq!(drawn, Some(EditCommand::AddBlock(Rect::new(0.0, 0.0, 40.0, 200.0))));
+        let drawn = drawn_shape(BuilderMode::Walls, &Point::new(40.0, 200.0), &Point::new(0.0, 0.0));
+        assert_eq!(drawn, Some(EditCommand::AddWall(LevelWallModel::new(0.0, 0.0, 40.0, 200.0))));
+        let drawn = drawn_shape(BuilderMode::Circles, &Point::new(0.0, 0.0), &Point::new(0.0, 30.0));
+        assert_eq!(drawn, Some(EditCommand::AddCircle(Circle::new(0.0, 0.0, 30.0))));
+        assert_eq!(drawn_shape(BuilderMode::Select, &Point::new(0.0, 0.0), &Point::new(40.0, 200.0)), None);
+    }
+}
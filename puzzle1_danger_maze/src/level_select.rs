@@ -0,0 +1,283 @@
+use wasm_bindgen::JsValue;
+use web_sys::CanvasRenderingContext2d;
+use std::vec::Vec;
+
+use crate::game_components::danger_bar::DangerBar;
+use crate::game_control::{GAME_HEIGHT, GAME_WIDTH};
+use crate::geometry;
+use crate::levels::level_model::LevelModel;
+use crate::levels::manifest::{LevelEntry, LevelManifest};
+use crate::progress::Progress;
+use crate::utils::{self, Point, Rect};
+
+const GRID_COLUMNS: usize = 4;
+const GRID_TOP: f64 = 140.0;
+const CELL_WIDTH: f64 = 290.0;
+const CELL_HEIGHT: f64 = 250.0;
+const THUMB_SCALE: f64 = 0.2;
+// Two rows fit on the canvas, any more levels go on further pages
+const PAGE_SIZE: usize = GRID_COLUMNS * 2;
+const PREV_BUTTON: (f64, f64, f64, f64) = (60.0, 670.0, 200.0, 80.0);
+const NEXT_BUTTON: (f64, f64, f64, f64) = (1020.0, 670.0, 200.0, 80.0);
+
+// Grid of the playable levels in a manifest, with a small preview of each
+pub struct LevelSelect {
+    entries: Vec::<LevelEntry>,
+    thumbnails: Vec::<Option<LevelModel>>,
+    page: usize
+}
+
+impl LevelSelect {
+    pub fn new(manifest: &LevelManifest) -> Self {
+        LevelSelect {
+            entries: manifest.levels.clone(),
+            thumbnails: manifest.levels.iter().map(|_| None).collect(),
+            page: 0
+        }
+    }
+
+    pub fn entries(&self) -> &Vec::<LevelEntry> {
+        &self.entries
+    }
+
+    pub fn has_thumbnail(&self, index: usize) -> bool {
+        matches!(self.thumbnails.get(index), Some(Some(_)))
+    }
+
+    pub fn set_thumbnail(&mut self, index: usize, level: LevelModel) {
+        if index < self.thumbnails.len() {
+            self.thumbnails[index] = Some(level);
+        }
+    }
+
+    // The first level is always open
+    pub fn is_unlocked(&self, index: usize, progress: &Progress) -> bool {
+        index == 0 || self.entries.get(index).map(|e| progress.is_unlocked(&e.id)).unwrap_or(false)
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.entries.len().div_ceil(PAGE_SIZE).max(1)
+    }
+
+    // Levels shown on the current page
+    fn page_levels(&self) -> std::ops::Range<usize> {
+        let first = self.page * PAGE_SIZE;
+        first..(first + PAGE_SIZE).min(self.entries.len())
+    }
+
+    // Where a level goes on its page
+    fn cell_rect(index: usize) -> Rect<f64> {
+        let index = index % PAGE_SIZE;
+        let left = (GAME_WIDTH - (GRID_COLUMNS as f64 * CELL_WIDTH)) / 2.0;
+        let col = (index % GRID_COLUMNS) as f64;
+        let row = (index / GRID_COLUMNS) as f64;
+        Rect::new(left + col * CELL_WIDTH, GRID_TOP + row * CELL_HEIGHT, CELL_WIDTH, CELL_HEIGHT)
+    }
+
+    fn button_rect(button: (f64, f64, f64, f64)) -> Rect<f64> {
+        Rect::new(button.0, button.1, button.2, button.3)
+    }
+
+    // Which unlocked level on this page, if any, was clicked
+    pub fn level_at(&self, x: f64, y: f64, progress: &Progress) -> Option<usize> {
+        self.page_levels().find(|index| {
+            geometry::point_in_rect(&Point::new(x, y), &Self::cell_rect(*index))
+                && self.is_unlocked(*index, progress)
+        })
+    }
+
+    // Moves to the previous or next page if one of their buttons was clicked
+    pub fn turn_page_at(&mut self, x: f64, y: f64) -> bool {
+        let pt = Point::new(x, y);
+        if self.page > 0 && geometry::point_in_rect(&pt, &Self::button_rect(PREV_BUTTON)) {
+            self.page -= 1;
+            return true;
+        }
+        if self.page + 1 < self.page_count() && geometry::point_in_rect(&pt, &Self::button_rect(NEXT_BUTTON)) {
+            self.page += 1;
+            return true;
+        }
+        false
+    }
+
+    pub fn render(&self, ctx: &mut CanvasRenderingContext2d, progress: &Progress) {
+        ctx.set_font("64px arial");
+        utils::drop_shadow_string(ctx, "Select Level".to_string(), 440.0, 90.0);
+
+        for index in self.page_levels() {
+            let entry = &self.entries[index];
+            let cell = Self::cell_rect(index);
+            let thumb_x = cell.loc.x + 15.0;
+            let thumb_y = cell.loc.y;
+
+            match &self.thumbnails[index] {
+                Some(level) => Self::render_thumbnail(ctx, level, thumb_x, thumb_y),
+                None => {
+                    ctx.set_fill_style(&JsValue::from("rgb(35, 35, 35)"));
+                    ctx.fill_rect(thumb_x, thumb_y, GAME_WIDTH * THUMB_SCALE, GAME_HEIGHT * THUMB_SCALE);
+                }
+            }
+
+            ctx.set_font("24px arial");
+            let title = format!("{}. {}", index + 1, entry.title);
+            utils::drop_shadow_string(ctx, title, thumb_x, thumb_y + 195.0);
+
+            if self.is_unlocked(index, progress) {
+                let best = match progress.best_time(&entry.id) {
+                    Some(time) => format!("Best: {}", utils::format_time(time)),
+                    None => "Not completed".to_string()
+                };
+                utils::drop_shadow_string(ctx, best, thumb_x, thumb_y + 225.0);
+            } else {
+                ctx.set_fill_style(&JsValue::from("rgba(0, 0, 0, 0.7)"));
+                ctx.fill_rect(thumb_x, thumb_y, GAME_WIDTH * THUMB_SCALE, GAME_HEIGHT * THUMB_SCALE);
+                ctx.set_font("32px arial");
+                utils::drop_shadow_string(ctx, "LOCKED".to_string(), thumb_x + 65.0, thumb_y + 95.0);
+            }
+        }
+
+        if self.page_count() > 1 {
+            ctx.set_font("32px arial");
+            let page = format!("Page {} / {}", self.page + 1, self.page_count());
+            utils::drop_shadow_string(ctx, page, 560.0, 722.0);
+            if self.page > 0 {
+                Self::render_button(ctx, PREV_BUTTON, "< Prev");
+            }
+            if self.page + 1 < self.page_count() {
+                Self::render_button(ctx, NEXT_BUTTON, "Next >");
+            }
+        }
+    }
+
+    fn render_button(ctx: &mut CanvasRenderingContext2d, button: (f64, f64, f64, f64), label: &str) {
+        ctx.set_fill_style(&JsValue::from("rgb(65, 65, 155)"));
+        ctx.fill_rect(button.0, button.1, button.2, button.3);
+        utils::drop_shadow_string(ctx, label.to_string(), button.0 + 45.0, button.1 + 52.0);
+    }
+
+    fn render_thumbnail(ctx: &mut CanvasRenderingContext2d, level: &LevelModel, x: f64, y: f64) {
+        ctx.save();
+        let _ = ctx.translate(x, y);
+        let _ = ctx.scale(THUMB_SCALE, THUMB_SCALE);
+
+        ctx.set_fill_style(&JsValue::from("rgb(55, 55, 55)"));
+        ctx.fill_rect(0.0, 0.0, GAME_WIDTH, GAME_HEIGHT);
+
+        ctx.set_fill_style(&JsValue::from("rgb(150, 150, 170)"));
+        for w in level.walls.iter() {
+            ctx.fill_rect(w.x, w.y, w.w, w.h);
+        }
+
+        ctx.set_fill_style(&JsValue::from("rgb(55, 255, 55)"));
+        for b in level.danger_blocks.iter() {
+            ctx.fill_rect(b.x, b.y, b.w, b.h);
+        }
+        for c in level.danger_circles.iter() {
+            Self::fill_circle(ctx, c.x, c.y, c.r);
+        }
+        for b in level.danger_bars.iter() {
+            let corners = DangerBar::from_model(b).start_rect().corners();
+            ctx.begin_path();
+            ctx.move_to(corners[0].x, corners[0].y);
+            for corner in corners[1..].iter() {
+                ctx.line_to(corner.x, corner.y);
+            }
+            ctx.close_path();
+            ctx.fill();
+        }
+        for p in level.danger_polygons.iter().filter(|p| !p.points.is_empty()) {
+            ctx.begin_path();
+            ctx.move_to(p.points[0].x, p.points[0].y);
+            for point in p.points[1..].iter() {
+                ctx.line_to(point.x, point.y);
+            }
+            ctx.close_path();
+            ctx.fill();
+        }
+
+        ctx.set_fill_style(&JsValue::from("rgb(165, 165, 255)"));
+        Self::fill_circle(ctx, level.goal.x, level.goal.y, 40.0);
+        ctx.set_fill_style(&JsValue::from("rgb(255, 255, 0)"));
+        Self::fill_circle(ctx, level.player.x, level.player.y, 20.0);
+
+        ctx.restore();
+    }
+
+    fn fill_circle(ctx: &mut CanvasRenderingContext2d, x: f64, y: f64, r: f64) {
+        ctx.begin_path();
+        let _ = ctx.arc(x, y, r, 0.0, std::f64::consts::PI * 2.0);
+        ctx.fill();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest() -> LevelManifest {
+        let entry = |id: &str| LevelEntry {
+            id: id.to_string(),
+            title: id.to_string(),
+            file: format!("{}.json", id)
+        };
+        LevelManifest {
+            name: "Test Pack".to_string(),
+            levels: (1..=6).map(|i| entry(&format!("level{}", i))).collect(),
+            credits: Some(entry("end"))
+        }
+    }
+
+    #[test]
+    fn only_unlocked_levels_can_be_picked() {
+        let select = LevelSelect::new(&manifest());
+        let mut progress = Progress::default();
+        let second = LevelSelect::cell_rect(1);
+        let (x, y) = (second.loc.x + 50.0, second.loc.y + 50.0);
+
+        assert_eq!(select.level_at(x, y, &progress), None);
+        progress.unlock("level2");
+        assert_eq!(select.level_at(x, y, &progress), Some(1));
+
+        let first = LevelSelect::cell_rect(0);
+        assert_eq!(select.level_at(first.loc.x + 10.0, first.loc.y + 10.0, &progress), Some(0));
+        assert_eq!(select.level_at(5.0, 5.0, &progress), None);
+    }
+
+    #[test]
+    fn grid_wraps_rows() {
+        assert_eq!(LevelSelect::cell_rect(4).loc.x, LevelSelect::cell_rect(0).loc.x);
+        assert!(LevelSelect::cell_rect(4).loc.y > LevelSelect::cell_rect(3).loc.y);
+        // Credits aren't offered in the grid
+        assert_eq!(LevelSelect::new(&manifest()).entries().len(), 6);
+    }
+
+    #[test]
+    fn long_packs_are_paged() {
+        let mut manifest = manifest();
+        manifest.levels = (1..=9).map(|i| LevelEntry {
+            id: format!("level{}", i),
+            title: format!("level{}", i),
+            file: format!("level{}.json", i)
+        }).collect();
+        let mut select = LevelSelect::new(&manifest);
+        assert_eq!(select.page_count(), 2);
+        // Every cell stays on the canvas
+        for index in 0..9 {
+            let cell = LevelSelect::cell_rect(index);
+            assert!(cell.loc.y + cell.height <= PREV_BUTTON.1, "level {}", index + 1);
+        }
+
+        let first = LevelSelect::cell_rect(0);
+        let (x, y) = (first.loc.x + 10.0, first.loc.y + 10.0);
+        assert_eq!(select.level_at(x, y, &Progress::default()), Some(0));
+        // No previous page, and the next button moves on to the ninth level
+        assert!(!select.turn_page_at(PREV_BUTTON.0 + 10.0, PREV_BUTTON.1 + 10.0));
+        assert!(select.turn_page_at(NEXT_BUTTON.0 + 10.0, NEXT_BUTTON.1 + 10.0));
+        assert!(!select.turn_page_at(NEXT_BUTTON.0 + 10.0, NEXT_BUTTON.1 + 10.0));
+        let mut progress = Progress::default();
+        progress.unlock("level9");
+        assert_eq!(select.level_at(x, y, &progress), Some(8));
+        assert!(select.turn_page_at(PREV_BUTTON.0 + 10.0, PREV_BUTTON.1 + 10.0));
+        assert_eq!(select.level_at(x, y, &progress), Some(0));
+    }
+}
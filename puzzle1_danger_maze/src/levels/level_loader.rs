@@ -0,0 +1,110 @@
+use std::fmt;
+
+use gloo_net::http::Request;
+use serde::de::DeserializeOwned;
+
+use super::level_model::LevelModel;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LevelLoadError {
+    // The request never got a response
+    Network { path: String, reason: String },
+    // No level file at that path
+    NotFound { path: String },
+    // Any other non-success status from the server
+    Status { path: String, status: u16 },
+    // The file is there but isn't valid
+    Parse { path: String, reason: String }
+}
+
+impl fmt::Display for LevelLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelLoadError::Network { path, reason } => write!(f, "Could not fetch {}: {}", path, reason),
+            LevelLoadError::NotFound { path } => write!(f, "{} does not exist", path),
+            LevelLoadError::Status { path, status } => write!(f, "{} returned status {}", path, status),
+            LevelLoadError::Parse { path, reason } => write!(f, "{} could not be read: {}", path, reason)
+        }
+    }
+}
+
+pub fn parse_json<T: DeserializeOwned>(path: &str, data: &str) -> Result<T, LevelLoadError> {
+    serde_json::from_str::<T>(data).map_err(|err| LevelLoadError::Parse {
+        path: path.to_string(),
+        reason: err.to_string()
+    })
+}
+
+pub async fn fetch_level(path: &str) -> Result<LevelModel, LevelLoadError> {
+    fetch_json::<LevelModel>(path).await
+}
+
+pub async fn fetch_json<T: DeserializeOwned>(path: &str) -> Result<T, LevelLoadError> {
+    let response = Request::get(path)
+        .send()
+        .await
+        .map_err(|err| LevelLoadError::Network { path: path.to_string(), reason: err.to_string() })?;
+
+    if response.status() == 404 {
+        return Err(LevelLoadError::NotFound { path: path.to_string() });
+    }
+    if !response.ok() {
+        return Err(LevelLoadError::Status { path: path.to_string(), status: response.status() });
+    }
+    // trunk serve hands back index.html for anything it can't find
+    let is_html = response.headers()
+        .get("content-type")
+        .map(|content_type| content_type.starts_with("text/html"))
+        .unwrap_or(false);
+    if is_html {
+        return Err(LevelLoadError::NotFound { path: path.to_string() });
+    }
+
+    let data = response.text()
+        .await
+        .map_err(|err| LevelLoadError::Network { path: path.to_string(), reason: err.to_string() })?;
+    parse_json::<T>(path, &data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_good_level() {
+        let data = r#"{
+            "player": {"x": 100.0, "y": 100.0},
+            "goal": {"x": 1150.0, "y": 400.0},
+            "danger_blocks": [{ "x": 440.0, "y": 200.0, "w": 400.0, "h": 400.0}],
+            "danger_circles": [],
+            "images": []
+        }"#;
+        let level = parse_json::<LevelModel>("level.json", data).unwrap();
+        assert_eq!(level.danger_blocks.len(), 1);
+    }
+
+    #[test]
+    fn export_round_trip() {
+        // The builder downloads levels with to_string_pretty
+        let dir = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("assets/levels");
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.file_name().unwrap() == "manifest.json" {
+                continue;
+            }
+            let data = std::fs::read_to_string(&path).unwrap();
+            let level = parse_json::<LevelModel>("level.json", &data).unwrap();
+            let exported = serde_json::to_string_pretty(&level).unwrap();
+            let original: serde_json::Value = serde_json::from_str(&data).unwrap();
+            let reloaded: serde_json::Value = serde_json::from_str(&exported).unwrap();
+            assert_eq!(original, reloaded, "{:?}", path);
+        }
+    }
+
+    #[test]
+    fn parse_bad_level() {
+        let err = parse_json::<LevelModel>("level.json", r#"{"player": {"x": 100.0}}"#).unwrap_err();
+        assert!(matches!(err, LevelLoadError::Parse { .. }));
+        assert!(err.to_string().starts_with("level.json could not be read"));
+    }
+}
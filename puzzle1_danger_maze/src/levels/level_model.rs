@@ -8,9 +8,22 @@ pub struct LevelModel {
     pub goal: LevelGoalModel,
     pub danger_blocks: Vec::<LevelBlockModel>,
     pub danger_circles: Vec::<LevelCircleModel>,
+    // Left out of the file when there are none, so older levels are unchanged
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub danger_bars: Vec::<LevelBarModel>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub danger_polygons: Vec::<LevelPolygonModel>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub walls: Vec::<LevelWallModel>,
     pub images: Vec::<LevelImageModel>
 }
 
+impl Default for LevelModel {
+    fn default() -> Self {
+        LevelModel::new()
+    }
+}
+
 impl LevelModel {
     pub fn new() -> Self {
         LevelModel {
@@ -18,6 +31,9 @@ impl LevelModel {
             goal: LevelGoalModel{x:0.0, y:0.0},
             danger_blocks: Vec::<LevelBlockModel>::new(),
             danger_circles: Vec::<LevelCircleModel>::new(),
+            danger_bars: Vec::<LevelBarModel>::new(),
+            danger_polygons: Vec::<LevelPolygonModel>::new(),
+            walls: Vec::<LevelWallModel>::new(),
             images: Vec::<LevelImageModel>::new()
         }
     }
@@ -38,13 +54,17 @@ pub struct LevelGoalModel {
     pub y: f64
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub struct LevelBlockModel {
     pub x: f64,
     pub y: f64,
     pub w: f64,
-    pub h: f64
+    pub h: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub motion: Option<LevelMotionModel>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pulse: Option<LevelPulseModel>
 }
 
 impl LevelBlockModel {
@@ -53,17 +73,23 @@ impl LevelBlockModel {
             x: x,
             y: y,
             w: w,
-            h: h
+            h: h,
+            motion: None,
+            pulse: None
         }
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub struct LevelCircleModel {
     pub x: f64,
     pub y: f64,
-    pub r: f64
+    pub r: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub motion: Option<LevelMotionModel>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pulse: Option<LevelPulseModel>
 }
 
 impl LevelCircleModel {
@@ -71,12 +97,127 @@ impl LevelCircleModel {
         LevelCircleModel {
             x: x,
             y: y,
-            r: r
+            r: r,
+            motion: None,
+            pulse: None
         }
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+// A bar spinning about its middle
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub struct LevelBarModel {
+    // The pivot
+    pub x: f64,
+    pub y: f64,
+    pub length: f64,
+    pub thickness: f64,
+    // Degrees per second, clockwise on screen
+    pub speed: f64,
+    // Degrees clockwise from pointing right, at the start of the level
+    #[serde(default)]
+    pub angle: f64
+}
+
+impl LevelBarModel {
+    pub fn new(x: f64, y: f64, length: f64, thickness: f64, speed: f64, angle: f64) -> Self {
+        LevelBarModel {
+            x,
+            y,
+            length,
+            thickness,
+            speed,
+            angle
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LevelMotionKind {
+    // Out along the points and back again
+    Linear,
+    // Along the points and straight back to the start
+    Loop,
+    // Round the first point, at whatever distance the shape was placed
+    Orbit
+}
+
+// How a hazard moves. Points are where the shape's centre goes, with its
+// placed position as the start of the path.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub struct LevelMotionModel {
+    pub kind: LevelMotionKind,
+    pub points: Vec::<LevelPointModel>,
+    // px per second
+    pub speed: f64,
+    // How far round the path it starts, from 0 to 1
+    #[serde(default)]
+    pub phase: f64
+}
+
+// Switches a hazard on and off. All times are in ms.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub struct LevelPulseModel {
+    pub on: f64,
+    pub off: f64,
+    // How far into the on/off cycle it is at the start
+    #[serde(default)]
+    pub phase: f64,
+    // Time spent growing from nothing when it switches on, and shrinking
+    // back before it goes off. Zero blinks straight on and off.
+    #[serde(default)]
+    pub grow: f64
+}
+
+// Safe to touch, but the player can't be dragged through it
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub struct LevelWallModel {
+    pub x: f64,
+    pub y: f64,
+    pub w: f64,
+    pub h: f64
+}
+
+impl LevelWallModel {
+    pub fn new(x: f64, y: f64, w: f64, h: f64) -> Self {
+        LevelWallModel {
+            x,
+            y,
+            w,
+            h
+        }
+    }
+}
+
+// Corners in order round the outline, which closes itself. Can be concave
+// but shouldn't cross over itself.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub struct LevelPolygonModel {
+    pub points: Vec::<LevelPointModel>
+}
+
+impl LevelPolygonModel {
+    pub fn new(points: &[(f64, f64)]) -> Self {
+        LevelPolygonModel {
+            points: points.iter().map(|(x, y)| LevelPointModel { x: *x, y: *y }).collect()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub struct LevelPointModel {
+    pub x: f64,
+    pub y: f64
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub struct LevelImageModel {
     pub filename: String,
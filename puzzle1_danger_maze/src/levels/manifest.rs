@@ -0,0 +1,111 @@
+use serde::*;
+use std::vec::Vec;
+
+use super::level_loader::{self, LevelLoadError};
+
+pub const DEFAULT_MANIFEST: &str = "assets/levels/manifest.json";
+
+// A level pack - the levels in the order they are played, plus an
+// optional credits screen shown once they are all done
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub struct LevelManifest {
+    pub name: String,
+    pub levels: Vec::<LevelEntry>,
+    #[serde(default)]
+    pub credits: Option<LevelEntry>
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub struct LevelEntry {
+    pub id: String,
+    pub title: String,
+    pub file: String
+}
+
+impl LevelManifest {
+    // Playable levels followed by the credits entry
+    pub fn len(&self) -> usize {
+        self.levels.len() + if self.credits.is_some() { 1 } else { 0 }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn entry(&self, index: usize) -> Option<&LevelEntry> {
+        if index < self.levels.len() {
+            self.levels.get(index)
+        } else if index == self.levels.len() {
+            self.credits.as_ref()
+        } else {
+            None
+        }
+    }
+
+    pub fn is_credits(&self, index: usize) -> bool {
+        self.credits.is_some() && index == self.levels.len()
+    }
+
+    // Look a playable level up by id, or by its number counting from 1
+    pub fn find(&self, level: &str) -> Option<usize> {
+        if let Some(index) = self.levels.iter().position(|entry| entry.id == level) {
+            return Some(index);
+        }
+        match level.parse::<usize>() {
+            Ok(num) if num >= 1 && num <= self.levels.len() => Some(num - 1),
+            _ => None
+        }
+    }
+}
+
+pub async fn fetch_manifest(path: &str) -> Result<LevelManifest, LevelLoadError> {
+    level_loader::fetch_json::<LevelManifest>(path).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest() -> LevelManifest {
+        level_loader::parse_json(DEFAULT_MANIFEST, r#"{
+            "name": "Test Pack",
+            "levels": [
+                {"id": "one", "title": "One", "file": "one.json"},
+                {"id": "two", "title": "Two", "file": "two.json"}
+            ],
+            "credits": {"id": "end", "title": "The End", "file": "end.json"}
+        }"#).unwrap()
+    }
+
+    #[test]
+    fn walks_levels_then_credits() {
+        let manifest = manifest();
+        assert_eq!(manifest.len(), 3);
+        assert_eq!(manifest.entry(1).unwrap().id, "two");
+        assert!(!manifest.is_credits(1));
+        assert_eq!(manifest.entry(2).unwrap().id, "end");
+        assert!(manifest.is_credits(2));
+        assert!(manifest.entry(3).is_none());
+    }
+
+    #[test]
+    fn find_by_id_or_number() {
+        let manifest = manifest();
+        assert_eq!(manifest.find("two"), Some(1));
+        assert_eq!(manifest.find("1"), Some(0));
+        assert_eq!(manifest.find("3"), None);
+        assert_eq!(manifest.find("0"), None);
+        assert_eq!(manifest.find("end"), None);
+    }
+
+    #[test]
+    fn credits_are_optional() {
+        let mut manifest = manifest();
+        manifest.credits = None;
+        assert_eq!(manifest.len(), 2);
+        assert!(!manifest.is_credits(2));
+        assert!(manifest.entry(2).is_none());
+    }
+}
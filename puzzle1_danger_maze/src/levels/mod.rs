@@ -1 +1,5 @@
-pub mod level_model;
\ No newline at end of file
+pub mod level_loader;
+pub mod level_model;
+pub mod manifest;
+pub mod solver;
+pub mod validator;
@@ -0,0 +1,667 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::f64::consts::PI;
+use std::fmt;
+use std::vec::Vec;
+
+use crate::game_components::{danger_bar::DangerBar, danger_block::DangerBlock, danger_circle::DangerCircle, danger_polygon::DangerPolygon, goal::Goal, player::PLAYER_SIZE, wall::Wall};
+use crate::game_control::{GAME_HEIGHT, GAME_WIDTH};
+use crate::geometry;
+use crate::{motion, pulse};
+use crate::utils::{self, Point};
+
+use super::level_model::{LevelBlockModel, LevelCircleModel, LevelModel, LevelMotionKind, LevelMotionModel};
+
+// Spacing of the search grid, in px. Gaps the player only just fits
+// through may be missed if they fall between grid points.
+pub const SOLVER_STEP: f64 = 5.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SolveError {
+    // The player would be reset as soon as it is picked up
+    PlayerBlocked,
+    PlayerOutside,
+    // Hazards cut the start off from the goal
+    GoalUnreachable
+}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolveError::PlayerBlocked => write!(f, "Player starts inside a hazard"),
+            SolveError::PlayerOutside => write!(f, "Player starts touching the border"),
+            SolveError::GoalUnreachable => write!(f, "There is no safe path to the goal")
+        }
+    }
+}
+
+// The narrowest point the player can't avoid. `clearance` is the distance
+// from the player's centre to the nearest hazard there, so the gap itself
+// is about twice as wide.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Gap {
+    pub at: Point<f64>,
+    pub clearance: f64
+}
+
+// A safe route from the player's start to somewhere that wins the level
+#[derive(Clone, Debug, PartialEq)]
+pub struct Solution {
+    pub path: Vec::<Point<f64>>,
+    pub length: f64,
+    pub gap: Gap,
+    // Only gets through by passing hazards that move or blink at the right
+    // moment, which the solver doesn't check
+    pub timed: bool
+}
+
+// Points round an orbit. The corners are pushed out so the edges stay
+// outside the circle, keeping the swept area on the safe side.
+const ORBIT_SIDES: usize = 32;
+
+// The level's hazards, tested with the same shapes as the game. Getting past
+// a moving or pulsing hazard is down to timing, so `new` only counts the ones
+// that are there the whole time and `swept` adds everywhere the rest reach.
+pub struct Obstacles {
+    blocks: Vec::<DangerBlock>,
+    circles: Vec::<DangerCircle>,
+    bars: Vec::<DangerBar>,
+    polygons: Vec::<DangerPolygon>,
+    // Safe to touch, but kept clear of like a hazard to keep the search simple
+    walls: Vec::<Wall>,
+    // (start, end, radius), for circles moving along a path and spinning bars
+    capsules: Vec::<(Point<f64>, Point<f64>, f64)>,
+    // Whether any hazards were left out by `new`
+    timed: bool
+}
+
+impl Obstacles {
+    pub fn new(level: &LevelModel) -> Self {
+        let blocks: Vec::<DangerBlock> = level.danger_blocks.iter().filter_map(still_block).collect();
+        let circles: Vec::<DangerCircle> = level.danger_circles.iter().filter_map(still_circle).collect();
+        let bars: Vec::<DangerBar> = level.danger_bars.iter().filter(|b| b.speed == 0.0).map(DangerBar::from_model).collect();
+        let timed = blocks.len() < level.danger_blocks.len()
+            || circles.len() < level.danger_circles.len()
+            || bars.len() < level.danger_bars.len();
+        Obstacles {
+            blocks,
+            circles,
+            bars,
+            polygons: level.danger_polygons.iter().map(DangerPolygon::from_model).collect(),
+            walls: level.walls.iter().map(Wall::from_model).collect(),
+            capsules: Vec::new(),
+            timed
+        }
+    }
+
+    // As `new`, plus all of the area the other hazards ever cover. A route
+    // clear of all of this doesn't need any timing.
+    pub fn swept(level: &LevelModel) -> Self {
+        let mut obstacles = Obstacles::new(level);
+        for model in level.danger_blocks.iter().filter(|b| still_block(b).is_none()) {
+            // Full size, and at home for the moment
+            let block = DangerBlock::new(model.x, model.y, model.w, model.h);
+            let Some(motion) = model.motion.as_ref().filter(|m| !motion::is_still(m, block.centre())) else {
+                obstacles.blocks.push(block);
+                continue;
+            };
+            let home = block.centre();
+            let rect = block.pos;
+            for (from, to) in swept_legs(motion, home) {
+                let mut corners = Vec::<Point<f64>>::new();
+                for at in [from, to] {
+                    let (x, y) = (rect.loc.x + at.x - home.x, rect.loc.y + at.y - home.y);
+                    corners.extend([
+                        Point::new(x, y),
+                        Point::new(x + rect.width, y),
+                        Point::new(x, y + rect.height),
+                        Point::new(x + rect.width, y + rect.height)
+                    ]);
+                }
+                obstacles.polygons.push(DangerPolygon::new(geometry::convex_hull(&corners)));
+            }
+        }
+        for model in level.danger_circles.iter().filter(|c| still_circle(c).is_none()) {
+            let home = Point::new(model.x, model.y);
+            match model.motion.as_ref().filter(|m| !motion::is_still(m, home)) {
+                Some(motion) => {
+                    for (from, to) in swept_legs(motion, home) {
+                        obstacles.capsules.push((from, to, model.r));
+                    }
+                },
+                None => obstacles.circles.push(DangerCircle::new(model.x, model.y, model.r))
+            }
+        }
+        for model in level.danger_bars.iter().filter(|b| b.speed != 0.0) {
+            // Spins right round, so covers the circle through its corners
+            let reach = (model.length / 2.0).hypot(model.thickness / 2.0);
+            let pivot = Point::new(model.x, model.y);
+            obstacles.capsules.push((pivot, pivot, reach));
+        }
+        obstacles
+    }
+
+    // Whether `new` left out any hazards that move, spin or blink
+    pub fn is_timed(&self) -> bool {
+        self.timed
+    }
+
+    // Distance from pt to the nearest hazard or the border. The player's
+    // centre is safe anywhere this is at least PLAYER_SIZE.
+    pub fn clearance(&self, pt: &Point<f64>) -> f64 {
+        let mut clearance = pt.x.min(GAME_WIDTH - pt.x).min(pt.y).min(GAME_HEIGHT - pt.y);
+        for b in self.blocks.iter() {
+            clearance = clearance.min(geometry::dist_point_to_rect(pt, &b.rect()));
+        }
+        for c in self.circles.iter() {
+            let circle = c.circle();
+            clearance = clearance.min(utils::dist_between_points(*pt, circle.loc) - circle.size);
+        }
+        for b in self.bars.iter() {
+            clearance = clearance.min(geometry::dist_point_to_oriented_rect(pt, &b.rect()));
+        }
+        for p in self.polygons.iter() {
+            clearance = clearance.min(geometry::dist_point_to_polygon(pt, &p.points));
+        }
+        for w in self.walls.iter() {
+            clearance = clearance.min(geometry::dist_point_to_rect(pt, &w.pos));
+        }
+        for (start, end, radius) in self.capsules.iter() {
+            clearance = clearance.min(geometry::dist_point_to_segment(pt, start, end) - radius);
+        }
+        clearance
+    }
+
+    // Whether the player can be dragged straight from start to end
+    pub fn clear_line(&self, start: &Point<f64>, end: &Point<f64>) -> bool {
+        let inside = |pt: &Point<f64>| {
+            pt.x >= PLAYER_SIZE && pt.x <= GAME_WIDTH - PLAYER_SIZE
+                && pt.y >= PLAYER_SIZE && pt.y <= GAME_HEIGHT - PLAYER_SIZE
+        };
+        // The safe area inside the border is convex, so the ends are enough
+        inside(start) && inside(end)
+            && !self.blocks.iter().any(|b| b.swept_hit(start, end, PLAYER_SIZE))
+            && !self.circles.iter().any(|c| c.swept_hit(start, end, PLAYER_SIZE))
+            && !self.bars.iter().any(|b| b.swept_hit(start, end, PLAYER_SIZE))
+            && !self.polygons.iter().any(|p| p.swept_hit(start, end, PLAYER_SIZE))
+            && !self.walls.iter().any(|w| geometry::capsule_intersects_rect(start, end, PLAYER_SIZE, &w.pos))
+            && !self.capsules.iter().any(|(a, b, radius)| geometry::dist_segment_to_segment(start, end, a, b) < radius + PLAYER_SIZE)
+    }
+}
+
+// The block as it is for the whole level, if it never moves or switches off.
+// A path with no speed still puts it wherever its phase says.
+fn still_block(model: &LevelBlockModel) -> Option<DangerBlock> {
+    let mut block = DangerBlock::from_model(model);
+    let moves = block.anim.motion.as_ref().is_some_and(|m| !motion::is_still(m, block.centre()));
+    let blinks = block.anim.pulse.as_ref().is_some_and(|p| !pulse::always_on(p));
+    if moves || blinks {
+        return None;
+    }
+    block.restart();
+    Some(block)
+}
+
+fn still_circle(model: &LevelCircleModel) -> Option<DangerCircle> {
+    let mut circle = DangerCircle::from_model(model);
+    let moves = circle.anim.motion.as_ref().is_some_and(|m| !motion::is_still(m, circle.pos.loc));
+    let blinks = circle.anim.pulse.as_ref().is_some_and(|p| !pulse::always_on(p));
+    if moves || blinks {
+        return None;
+    }
+    circle.restart();
+    Some(circle)
+}
+
+// Every leg of the centre's path, as absolute points
+fn swept_legs(motion: &LevelMotionModel, home: Point<f64>) -> Vec::<(Point<f64>, Point<f64>)> {
+    let points = match motion.kind {
+        LevelMotionKind::Orbit => {
+            let centre = Point::new(motion.points[0].x, motion.points[0].y);
+            let radius = utils::dist_between_points(home, centre) / (PI / ORBIT_SIDES as f64).cos();
+            (0..ORBIT_SIDES).map(|i| {
+                let angle = 2.0 * PI * i as f64 / ORBIT_SIDES as f64;
+                Point::new(centre.x + radius * angle.cos(), centre.y + radius * angle.sin())
+            }).collect()
+        },
+        _ => motion::path_points(motion, home)
+    };
+    (0..points.len()).map(|i| (points[i], points[(i + 1) % points.len()])).collect()
+}
+
+// Grid of points over the canvas, lined up so the player starts on one
+pub struct SearchGrid {
+    origin: Point<f64>,
+    step: f64,
+    cols: usize,
+    rows: usize,
+    clearance: Vec::<f64>
+}
+
+impl SearchGrid {
+    pub fn new(obstacles: &Obstacles, start: &Point<f64>, step: f64) -> Self {
+        let origin = Point::new(start.x.rem_euclid(step), start.y.rem_euclid(step));
+        let cols = ((GAME_WIDTH - origin.x) / step).floor() as usize + 1;
+        let rows = ((GAME_HEIGHT - origin.y) / step).floor() as usize + 1;
+        let mut grid = SearchGrid {
+            origin,
+            step,
+            cols,
+            rows,
+            clearance: Vec::with_capacity(cols * rows)
+        };
+        for index in 0..cols * rows {
+            let clearance = obstacles.clearance(&grid.point(index));
+            grid.clearance.push(clearance);
+        }
+        grid
+    }
+
+    fn len(&self) -> usize {
+        self.cols * self.rows
+    }
+
+    pub fn point(&self, index: usize) -> Point<f64> {
+        let col = (index % self.cols) as f64;
+        let row = (index / self.cols) as f64;
+        Point::new(self.origin.x + col * self.step, self.origin.y + row * self.step)
+    }
+
+    pub fn index_of(&self, pt: &Point<f64>) -> Option<usize> {
+        let col = ((pt.x - self.origin.x) / self.step).round();
+        let row = ((pt.y - self.origin.y) / self.step).round();
+        if col < 0.0 || row < 0.0 || col as usize >= self.cols || row as usize >= self.rows {
+            return None;
+        }
+        Some(row as usize * self.cols + col as usize)
+    }
+
+    pub fn is_free(&self, index: usize) -> bool {
+        self.clearance[index] >= PLAYER_SIZE
+    }
+
+    // Eight way neighbours. Diagonals need both sides free so the
+    // path can't squeeze between two touching corners.
+    pub fn neighbours(&self, index: usize) -> Vec::<(usize, f64)> {
+        let col = (index % self.cols) as i64;
+        let row = (index / self.cols) as i64;
+        let cell = |c: i64, r: i64| -> Option<usize> {
+            if c < 0 || r < 0 || c >= self.cols as i64 || r >= self.rows as i64 {
+                return None;
+            }
+            Some(r as usize * self.cols + c as usize).filter(|i| self.is_free(*i))
+        };
+
+        let mut ret = Vec::with_capacity(8);
+        for (dc, dr) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+            if let Some(next) = cell(col + dc, row + dr) {
+                ret.push((next, self.step));
+            }
+        }
+        for (dc, dr) in [(1, 1), (1, -1), (-1, 1), (-1, -1)] {
+            let sides_free = cell(col + dc, row).is_some() && cell(col, row + dr).is_some();
+            if let (true, Some(next)) = (sides_free, cell(col + dc, row + dr)) {
+                ret.push((next, self.step * std::f64::consts::SQRT_2));
+            }
+        }
+        ret
+    }
+}
+
+// Open list entry, ordered so the BinaryHeap pops the lowest cost first
+struct Open {
+    cost: f64,
+    index: usize
+}
+
+impl PartialEq for Open {
+    fn eq(&self, other: &Self) -> bool {
+        self.cost == other.cost
+    }
+}
+
+impl Eq for Open {}
+
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Open {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+// Closest the player has to get to the goal's centre to win
+pub fn win_distance(level: &LevelModel) -> f64 {
+    Goal::new(level.goal.x, level.goal.y).get_dist() + PLAYER_SIZE
+}
+
+// Keeping clear of everywhere the moving and blinking hazards ever reach needs
+// no timing, so that is tried first. Failing that, the route past just the
+// hazards that are always there is returned, marked as timed.
+pub fn solve(level: &LevelModel) -> Result<Solution, SolveError> {
+    let swept = Obstacles::swept(level);
+    let result = solve_with(level, &swept);
+    if result.is_ok() || !swept.is_timed() {
+        return result;
+    }
+    solve_with(level, &Obstacles::new(level)).map(|solution| Solution { timed: true, ..solution })
+}
+
+fn solve_with(level: &LevelModel, obstacles: &Obstacles) -> Result<Solution, SolveError> {
+    let start = Point::new(level.player.x, level.player.y);
+    let grid = SearchGrid::new(obstacles, &start, SOLVER_STEP);
+    solve_on_grid(level, obstacles, &grid)
+}
+
+pub fn solve_on_grid(level: &LevelModel, obstacles: &Obstacles, grid: &SearchGrid) -> Result<Solution, SolveError> {
+    let start = Point::new(level.player.x, level.player.y);
+    let goal = Point::new(level.goal.x, level.goal.y);
+    let win_dist = win_distance(level);
+
+    let border = start.x.min(GAME_WIDTH - start.x).min(start.y).min(GAME_HEIGHT - start.y);
+    if border < PLAYER_SIZE {
+        return Err(SolveError::PlayerOutside);
+    }
+    let start_index = match grid.index_of(&start) {
+        Some(index) if grid.is_free(index) => index,
+        _ => return Err(SolveError::PlayerBlocked)
+    };
+
+    // A* towards the edge of the win radius
+    let heuristic = |index: usize| (utils::dist_between_points(grid.point(index), goal) - win_dist).max(0.0);
+    let mut cost = vec![f64::INFINITY; grid.len()];
+    let mut came_from = vec![usize::MAX; grid.len()];
+    let mut open = BinaryHeap::new();
+    cost[start_index] = 0.0;
+    open.push(Open { cost: heuristic(start_index), index: start_index });
+
+    while let Some(Open { index, .. }) = open.pop() {
+        if utils::dist_between_points(grid.point(index), goal) < win_dist {
+            let cells = trace_back(&came_from, index);
+            let path = smooth_path(obstacles, cells.iter().map(|i| grid.point(*i)).collect());
+            // Found a way, so the widest route has to succeed too
+            let gap = tightest_gap(grid, start_index, &goal, win_dist).unwrap_or(Gap {
+                at: start,
+                clearance: grid.clearance[start_index]
+            });
+            return Ok(Solution {
+                length: path_length(&path),
+                path,
+                gap,
+                timed: false
+            });
+        }
+        for (next, step) in grid.neighbours(index) {
+            let next_cost = cost[index] + step;
+            if next_cost < cost[next] {
+                cost[next] = next_cost;
+                came_from[next] = index;
+                open.push(Open { cost: next_cost + heuristic(next), index: next });
+            }
+        }
+    }
+    Err(SolveError::GoalUnreachable)
+}
+
+fn trace_back(came_from: &[usize], end: usize) -> Vec::<usize> {
+    let mut cells = vec![end];
+    while let Some(prev) = cells.last().map(|i| came_from[*i]).filter(|prev| *prev != usize::MAX) {
+        cells.push(prev);
+    }
+    cells.reverse();
+    cells
+}
+
+// Search for the route that stays furthest from the hazards at its
+// tightest point, and report where that point is
+fn tightest_gap(grid: &SearchGrid, start_index: usize, goal: &Point<f64>, win_dist: f64) -> Option<Gap> {
+    let mut best = vec![f64::NEG_INFINITY; grid.len()];
+    let mut came_from = vec![usize::MAX; grid.len()];
+    let mut open = BinaryHeap::new();
+    best[start_index] = grid.clearance[start_index];
+    // Lowest cost pops first, so the widest routes go in negated
+    open.push(Open { cost: -best[start_index], index: start_index });
+
+    while let Some(Open { index, .. }) = open.pop() {
+        if utils::dist_between_points(grid.point(index), *goal) < win_dist {
+            let cells = trace_back(&came_from, index);
+            let pinch = cells.iter().copied().min_by(|a, b| grid.clearance[*a].total_cmp(&grid.clearance[*b]))?;
+            return Some(Gap {
+                at: grid.point(pinch),
+                clearance: grid.clearance[pinch]
+            });
+        }
+        for (next, _) in grid.neighbours(index) {
+            let width = best[index].min(grid.clearance[next]);
+            if width > best[next] {
+                best[next] = width;
+                came_from[next] = index;
+                open.push(Open { cost: -width, index: next });
+            }
+        }
+    }
+    None
+}
+
+// Pull the grid path tight by skipping points the player can drag straight past
+fn smooth_path(obstacles: &Obstacles, path: Vec::<Point<f64>>) -> Vec::<Point<f64>> {
+    if path.len() <= 2 {
+        return path;
+    }
+    let mut ret = vec![path[0]];
+    let mut from = 0;
+    while from < path.len() - 1 {
+        let to = (from + 1..path.len())
+            .rev()
+            .find(|to| *to == from + 1 || obstacles.clear_line(&path[from], &path[*to]))
+            .unwrap_or(from + 1);
+        ret.push(path[to]);
+        from = to;
+    }
+    ret
+}
+
+pub fn path_length(path: &[Point<f64>]) -> f64 {
+    path.windows(2).map(|pair| utils::dist_between_points(pair[0], pair[1])).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::levels::level_model::{LevelBarModel, LevelPointModel, LevelPolygonModel, LevelPulseModel, LevelWallModel};
+    use crate::simulation::{PointerInput, Simulation, SIM_STEP};
+    use crate::test_support::{load_level, load_manifest, moves, open_level};
+
+    // Drag the player along the path in small moves
+    fn follow(level: &LevelModel, path: &[Point<f64>]) -> (u32, bool) {
+        let mut inputs = vec![PointerInput::Down(path[0].x, path[0].y)];
+        for pair in path.windows(2) {
+            let steps = (utils::dist_between_points(pair[0], pair[1]) / 5.0).ceil().max(1.0) as usize;
+            inputs.extend(moves((pair[0].x, pair[0].y), (pair[1].x, pair[1].y), steps));
+        }
+        let report = Simulation::run(level, &inputs, SIM_STEP);
+        (report.resets, report.won)
+    }
+
+    #[test]
+    fn straight_line_on_an_empty_level() {
+        let level = open_level();
+        let solution = solve(&level).unwrap();
+        assert_eq!(solution.path.len(), 2);
+        assert!((solution.length - (1050.0 - win_distance(&level))).abs() <= SOLVER_STEP);
+    }
+
+    #[test]
+    fn wall_of_circles_blocks_the_goal() {
+        let mut level = open_level();
+        for i in 0..=10 {
+            level.danger_circles.push(LevelCircleModel::new(640.0, i as f64 * 80.0, 45.0));
+        }
+        assert_eq!(solve(&level), Err(SolveError::GoalUnreachable));
+
+        // Take one out and there's a gap again
+        level.danger_circles.remove(5);
+        assert!(solve(&level).is_ok());
+    }
+
+    #[test]
+    fn pulsing_hazards_can_be_timed() {
+        let mut level = open_level();
+        let mut wall = LevelBlockModel::new(600.0, 0.0, 100.0, 800.0);
+        wall.pulse = Some(LevelPulseModel { on: 1000.0, off: 1000.0, phase: 0.0, grow: 0.0 });
+        level.danger_blocks.push(wall);
+        assert!(solve(&level).unwrap().timed);
+
+        level.danger_blocks[0].pulse = None;
+        assert_eq!(solve(&level), Err(SolveError::GoalUnreachable));
+    }
+
+    #[test]
+    fn finds_the_tightest_gap() {
+        let mut level = open_level();
+        // A wall with a 100px slot centred on the player's row
+        level.danger_blocks.push(LevelBlockModel::new(600.0, 0.0, 100.0, 350.0));
+        level.danger_blocks.push(LevelBlockModel::new(600.0, 450.0, 100.0, 350.0));
+        let solution = solve(&level).unwrap();
+        assert_eq!(solution.gap.clearance, 50.0);
+        assert!(solution.gap.at.x >= 600.0 && solution.gap.at.x <= 700.0);
+        assert_eq!(solution.gap.at.y, 400.0);
+
+        // Open levels are only limited by the border
+        let solution = solve(&open_level()).unwrap();
+        assert!(solution.gap.clearance >= 100.0);
+    }
+
+    #[test]
+    fn only_still_bars_block() {
+        let mut level = open_level();
+        // Top to bottom down the middle, but tilted
+        level.danger_bars.push(LevelBarModel::new(640.0, 400.0, 900.0, 20.0, 0.0, 80.0));
+        assert_eq!(solve(&level), Err(SolveError::GoalUnreachable));
+
+        level.danger_bars[0].speed = 90.0;
+        assert!(solve(&level).unwrap().timed);
+    }
+
+    #[test]
+    fn pulses_that_stay_on_block() {
+        let mut level = open_level();
+        let mut wall = LevelBlockModel::new(600.0, 0.0, 100.0, 800.0);
+        wall.pulse = Some(LevelPulseModel { on: 1000.0, off: 0.0, phase: 0.0, grow: 0.0 });
+        level.danger_blocks.push(wall);
+        assert_eq!(solve(&level), Err(SolveError::GoalUnreachable));
+
+        // Same for circles, and one gap in them has to be timed
+        let mut level = open_level();
+        for i in 0..=10 {
+            let mut circle = LevelCircleModel::new(640.0, i as f64 * 80.0, 45.0);
+            circle.pulse = Some(LevelPulseModel { on: 1000.0, off: 0.0, phase: 0.0, grow: 0.0 });
+            level.danger_circles.push(circle);
+        }
+        assert_eq!(solve(&level), Err(SolveError::GoalUnreachable));
+        level.danger_circles[5].pulse = Some(LevelPulseModel { on: 1000.0, off: 1000.0, phase: 0.0, grow: 0.0 });
+        assert!(solve(&level).unwrap().timed);
+    }
+
+    #[test]
+    fn paths_that_go_nowhere_still_block() {
+        let mut level = open_level();
+        let mut wall = LevelBlockModel::new(600.0, 0.0, 100.0, 800.0);
+        wall.motion = Some(LevelMotionModel {
+            kind: LevelMotionKind::Linear,
+            points: vec![LevelPointModel { x: 650.0, y: 100.0 }],
+            speed: 0.0,
+            phase: 0.0
+        });
+        level.danger_blocks.push(wall);
+        assert_eq!(solve(&level), Err(SolveError::GoalUnreachable));
+    }
+
+    #[test]
+    fn keeps_clear_of_moving_hazards_if_it_can() {
+        let mut level = open_level();
+        // Up and down in the top corner, nowhere near the way across
+        let mut block = LevelBlockModel::new(1000.0, 50.0, 50.0, 50.0);
+        block.motion = Some(LevelMotionModel {
+            kind: LevelMotionKind::Linear,
+            points: vec![LevelPointModel { x: 1025.0, y: 200.0 }],
+            speed: 100.0,
+            phase: 0.0
+        });
+        level.danger_blocks.push(block);
+        let solution = solve(&level).unwrap();
+        assert!(!solution.timed);
+
+        // Round and round across the whole height, so it has to be dodged
+        let mut circle = LevelCircleModel::new(640.0, 100.0, 60.0);
+        circle.motion = Some(LevelMotionModel {
+            kind: LevelMotionKind::Orbit,
+            points: vec![LevelPointModel { x: 640.0, y: 400.0 }],
+            speed: 300.0,
+            phase: 0.0
+        });
+        level.danger_circles.push(circle);
+        let solution = solve(&level).unwrap();
+        assert!(solution.timed);
+        // Only passes where it starts
+        let obstacles = Obstacles::swept(&level);
+        assert!(obstacles.clearance(&Point::new(640.0, 700.0)) < 0.0);
+        assert!(obstacles.clearance(&Point::new(940.0, 400.0)) < 0.0);
+        assert!(obstacles.clearance(&Point::new(640.0, 400.0)) > PLAYER_SIZE);
+    }
+
+    #[test]
+    fn round_a_diagonal_wall() {
+        let mut level = open_level();
+        // A slanted wall from the top, leaving a gap at the bottom
+        level.danger_polygons.push(LevelPolygonModel::new(&[(500.0, 0.0), (560.0, 0.0), (760.0, 650.0), (700.0, 650.0)]));
+        let solution = solve(&level).unwrap();
+        assert!(solution.path.iter().any(|p| p.y > 650.0));
+        let (resets, won) = follow(&level, &solution.path);
+        assert!(won);
+        assert_eq!(resets, 0);
+
+        // Down to the floor, and there's no way round
+        level.danger_polygons[0].points[2].y = 800.0;
+        level.danger_polygons[0].points[3].y = 800.0;
+        assert_eq!(solve(&level), Err(SolveError::GoalUnreachable));
+    }
+
+    #[test]
+    fn walls_block_the_way_too() {
+        let mut level = open_level();
+        level.walls.push(LevelWallModel::new(600.0, 0.0, 40.0, 800.0));
+        assert_eq!(solve(&level), Err(SolveError::GoalUnreachable));
+
+        level.walls[0].h = 600.0;
+        let solution = solve(&level).unwrap();
+        let (resets, won) = follow(&level, &solution.path);
+        assert!(won);
+        assert_eq!(resets, 0);
+    }
+
+    #[test]
+    fn blocked_start() {
+        let mut level = open_level();
+        level.danger_blocks.push(LevelBlockModel::new(90.0, 390.0, 20.0, 20.0));
+        assert_eq!(solve(&level), Err(SolveError::PlayerBlocked));
+
+        let mut level = open_level();
+        level.player.x = 10.0;
+        assert_eq!(solve(&level), Err(SolveError::PlayerOutside));
+    }
+
+    #[test]
+    fn shipped_levels_can_be_won_along_the_path() {
+        for entry in load_manifest().levels.iter() {
+            let level = load_level(&entry.file);
+            let solution = solve(&level).unwrap_or_else(|err| panic!("{}: {}", entry.file, err));
+            assert_eq!(follow(&level, &solution.path), (0, true), "{}", entry.file);
+        }
+    }
+}
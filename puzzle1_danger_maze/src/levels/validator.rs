@@ -0,0 +1,394 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::vec::Vec;
+
+use crate::game_components::{danger_bar::DangerBar, danger_block::DangerBlock, danger_circle::DangerCircle, danger_polygon::DangerPolygon, player::{Player, PLAYER_SIZE}, wall::Wall};
+use crate::game_control::{GAME_HEIGHT, GAME_WIDTH};
+use crate::geometry;
+use crate::{motion, pulse};
+use crate::utils::Point;
+
+use super::level_loader;
+use super::level_model::LevelModel;
+use super::solver::{self, SolveError};
+
+// Something wrong with a level file. The game copes with all of these but
+// they are almost always a mistake in the builder.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LevelIssue {
+    // Missing, or not a LevelModel
+    Unreadable(String),
+    // Width or height below zero, from dragging up or left
+    NegativeBlock { index: usize },
+    EmptyBlock { index: usize },
+    EmptyCircle { index: usize },
+    EmptyBar { index: usize },
+    // Fewer than three corners, or all in a line
+    EmptyPolygon { index: usize },
+    // The game and the canvas would disagree about what is inside
+    CrossedPolygon { index: usize },
+    NegativeWall { index: usize },
+    EmptyWall { index: usize },
+    // Has a path, but no speed or nowhere to go
+    StillBlock { index: usize },
+    StillCircle { index: usize },
+    // Pulses with no time switched on
+    DarkBlock { index: usize },
+    DarkCircle { index: usize },
+    PlayerOutside,
+    GoalOutside,
+    PlayerBlocked,
+    GoalBlocked,
+    // Only checked when the files are to hand, see check_file
+    MissingImage { filename: String },
+    Unsolvable(SolveError),
+    // Solvable, but only by timing moves past hazards, which isn't checked
+    NeedsTiming
+}
+
+impl fmt::Display for LevelIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelIssue::Unreadable(reason) => write!(f, "{}", reason),
+            LevelIssue::NegativeBlock { index } => write!(f, "Block {} has a negative size", index),
+            LevelIssue::EmptyBlock { index } => write!(f, "Block {} has no area", index),
+            LevelIssue::EmptyCircle { index } => write!(f, "Circle {} has no radius", index),
+            LevelIssue::EmptyBar { index } => write!(f, "Bar {} has no length or thickness", index),
+            LevelIssue::EmptyPolygon { index } => write!(f, "Polygon {} has no area", index),
+            LevelIssue::CrossedPolygon { index } => write!(f, "Polygon {} crosses over itself", index),
+            LevelIssue::NegativeWall { index } => write!(f, "Wall {} has a negative size", index),
+            LevelIssue::EmptyWall { index } => write!(f, "Wall {} has no area", index),
+            LevelIssue::StillBlock { index } => write!(f, "Block {} has a path that goes nowhere", index),
+            LevelIssue::StillCircle { index } => write!(f, "Circle {} has a path that goes nowhere", index),
+            LevelIssue::DarkBlock { index } => write!(f, "Block {} never switches on", index),
+            LevelIssue::DarkCircle { index } => write!(f, "Circle {} never switches on", index),
+            LevelIssue::PlayerOutside => write!(f, "Player starts touching the border"),
+            LevelIssue::GoalOutside => write!(f, "Goal is outside the play area"),
+            LevelIssue::PlayerBlocked => write!(f, "Player starts inside a hazard or wall"),
+            LevelIssue::GoalBlocked => write!(f, "Goal is inside a hazard or wall"),
+            LevelIssue::MissingImage { filename } => write!(f, "Image {} does not exist", filename),
+            LevelIssue::Unsolvable(err) => write!(f, "{}", err),
+            LevelIssue::NeedsTiming => write!(f, "Every path needs timing past moving hazards, not checked")
+        }
+    }
+}
+
+impl LevelIssue {
+    // Worth a look, but the level can still ship
+    pub fn is_warning(&self) -> bool {
+        matches!(self, LevelIssue::NeedsTiming)
+    }
+}
+
+fn in_bounds(x: f64, y: f64, margin: f64) -> bool {
+    x >= margin && x <= GAME_WIDTH - margin && y >= margin && y <= GAME_HEIGHT - margin
+}
+
+// Problems that stop the level being played come first
+pub fn validate(level: &LevelModel) -> Vec::<LevelIssue> {
+    let mut issues = Vec::<LevelIssue>::new();
+
+    // Uses the same shapes and tests as the game, moving ones where they start
+    let mut blocks: Vec::<DangerBlock> = level.danger_blocks.iter().map(DangerBlock::from_model).collect();
+    blocks.iter_mut().for_each(|b| b.restart());
+    let mut circles: Vec::<DangerCircle> = level.danger_circles.iter().map(DangerCircle::from_model).collect();
+    circles.iter_mut().for_each(|c| c.restart());
+    let bars: Vec::<DangerBar> = level.danger_bars.iter().map(DangerBar::from_model).collect();
+    let polygons: Vec::<DangerPolygon> = level.danger_polygons.iter().map(DangerPolygon::from_model).collect();
+    let walls: Vec::<Wall> = level.walls.iter().map(Wall::from_model).collect();
+
+    let player = Player::new(level.player.x, level.player.y).get_circle();
+    let player_blocked = blocks.iter().any(|b| b.circle_inside(&player))
+        || circles.iter().any(|c| c.circle_inside(&player))
+        || bars.iter().any(|b| b.circle_inside(&player))
+        || polygons.iter().any(|p| p.circle_inside(&player))
+        || walls.iter().any(|w| w.circle_inside(&player));
+    if player_blocked {
+        issues.push(LevelIssue::PlayerBlocked);
+    }
+    let goal = Point::new(level.goal.x, level.goal.y);
+    let goal_blocked = blocks.iter().any(|b| b.point_inside(goal.x, goal.y))
+        || circles.iter().any(|c| geometry::point_in_circle(&goal, &c.circle()))
+        || bars.iter().any(|b| b.point_inside(goal.x, goal.y))
+        || polygons.iter().any(|p| p.point_inside(goal.x, goal.y))
+        || walls.iter().any(|w| w.point_inside(goal.x, goal.y));
+    if goal_blocked {
+        issues.push(LevelIssue::GoalBlocked);
+    }
+    // The game resets the player as soon as it touches the border
+    if !in_bounds(level.player.x, level.player.y, PLAYER_SIZE) {
+        issues.push(LevelIssue::PlayerOutside);
+    }
+    if !in_bounds(level.goal.x, level.goal.y, 0.0) {
+        issues.push(LevelIssue::GoalOutside);
+    }
+
+    for (index, b) in level.danger_blocks.iter().enumerate() {
+        if b.w == 0.0 || b.h == 0.0 {
+            issues.push(LevelIssue::EmptyBlock { index });
+        } else if b.w < 0.0 || b.h < 0.0 {
+            issues.push(LevelIssue::NegativeBlock { index });
+        }
+    }
+    for (index, c) in level.danger_circles.iter().enumerate() {
+        if c.r <= 0.0 {
+            issues.push(LevelIssue::EmptyCircle { index });
+        }
+    }
+
+    for (index, b) in level.danger_bars.iter().enumerate() {
+        if b.length <= 0.0 || b.thickness <= 0.0 {
+            issues.push(LevelIssue::EmptyBar { index });
+        }
+    }
+    for (index, p) in polygons.iter().enumerate() {
+        if p.points.len() < 3 || geometry::polygon_area(&p.points) == 0.0 {
+            issues.push(LevelIssue::EmptyPolygon { index });
+        } else if geometry::polygon_crosses_itself(&p.points) {
+            issues.push(LevelIssue::CrossedPolygon { index });
+        }
+    }
+    for (index, w) in level.walls.iter().enumerate() {
+        if w.w == 0.0 || w.h == 0.0 {
+            issues.push(LevelIssue::EmptyWall { index });
+        } else if w.w < 0.0 || w.h < 0.0 {
+            issues.push(LevelIssue::NegativeWall { index });
+        }
+    }
+
+    for (index, b) in blocks.iter().enumerate() {
+        if b.anim.motion.as_ref().is_some_and(|m| motion::is_still(m, b.centre())) {
+            issues.push(LevelIssue::StillBlock { index });
+        }
+    }
+    for (index, c) in circles.iter().enumerate() {
+        if c.anim.motion.as_ref().is_some_and(|m| motion::is_still(m, c.pos.loc)) {
+            issues.push(LevelIssue::StillCircle { index });
+        }
+    }
+
+    for (index, b) in blocks.iter().enumerate() {
+        if b.anim.pulse.as_ref().is_some_and(pulse::never_on) {
+            issues.push(LevelIssue::DarkBlock { index });
+        }
+    }
+    for (index, c) in circles.iter().enumerate() {
+        if c.anim.pulse.as_ref().is_some_and(pulse::never_on) {
+            issues.push(LevelIssue::DarkCircle { index });
+        }
+    }
+    issues
+}
+
+// Full check of a level file for the validate_levels tool. Paths are
+// relative to `root`, the crate directory that trunk serves. The credits
+// level is allowed its goal off screen, so it also isn't solved.
+pub fn check_file(root: &Path, file: &str, credits: bool) -> Vec::<LevelIssue> {
+    let data = match fs::read_to_string(root.join(file)) {
+        Ok(data) => data,
+        Err(err) => return vec![LevelIssue::Unreadable(format!("{} could not be opened: {}", file, err))]
+    };
+    let level = match level_loader::parse_json::<LevelModel>(file, &data) {
+        Ok(level) => level,
+        Err(err) => return vec![LevelIssue::Unreadable(err.to_string())]
+    };
+
+    let mut issues = validate(&level);
+    if credits {
+        issues.retain(|issue| *issue != LevelIssue::GoalOutside);
+    }
+    for image in level.images.iter() {
+        if !root.join(image.filename.trim_start_matches("./")).is_file() {
+            issues.push(LevelIssue::MissingImage { filename: image.filename.clone() });
+        }
+    }
+    // Not worth searching a level that is already known to be broken, but
+    // missing art doesn't change where the player can go
+    let broken = issues.iter().any(|issue| !matches!(issue, LevelIssue::MissingImage { .. }));
+    if !credits && !broken {
+        match solver::solve(&level) {
+            Ok(solution) if solution.timed => issues.push(LevelIssue::NeedsTiming),
+            Ok(_) => {},
+            Err(err) => issues.push(LevelIssue::Unsolvable(err))
+        }
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::levels::level_model::{LevelBarModel, LevelBlockModel, LevelCircleModel, LevelImageModel, LevelMotionKind, LevelMotionModel, LevelPointModel, LevelPolygonModel, LevelPulseModel, LevelWallModel};
+    use crate::levels::manifest::DEFAULT_MANIFEST;
+    use crate::test_support::{crate_root, load_level, load_manifest, open_level};
+
+    #[test]
+    fn flags_negative_and_empty_shapes() {
+        let mut level = open_level();
+        level.danger_blocks.push(LevelBlockModel::new(500.0, 500.0, 100.0, 100.0));
+        level.danger_blocks.push(LevelBlockModel::new(500.0, 500.0, -100.0, 100.0));
+        level.danger_blocks.push(LevelBlockModel::new(500.0, 500.0, 0.0, 100.0));
+        level.danger_circles.push(LevelCircleModel::new(300.0, 300.0, 0.0));
+        assert_eq!(validate(&level), vec![
+            LevelIssue::NegativeBlock { index: 1 },
+            LevelIssue::EmptyBlock { index: 2 },
+            LevelIssue::EmptyCircle { index: 0 }
+        ]);
+    }
+
+    #[test]
+    fn flags_blocked_markers() {
+        let mut level = open_level();
+        // Negative block still covers the player once normalised
+        level.danger_blocks.push(LevelBlockModel::new(150.0, 450.0, -100.0, -100.0));
+        level.danger_circles.push(LevelCircleModel::new(1150.0, 400.0, 50.0));
+        level.goal.x = 1300.0;
+        let issues = validate(&level);
+        assert_eq!(issues[0], LevelIssue::PlayerBlocked);
+        assert!(issues.contains(&LevelIssue::GoalOutside));
+        assert!(issues.contains(&LevelIssue::NegativeBlock { index: 0 }));
+    }
+
+    #[test]
+    fn shipped_levels_are_clean() {
+        // The credits keep their goal off screen on purpose
+        for entry in load_manifest().levels.iter() {
+            assert_eq!(validate(&load_level(&entry.file)), vec![], "{}", entry.file);
+        }
+    }
+
+    #[test]
+    fn flags_paths_that_go_nowhere() {
+        let mut level = open_level();
+        let mut block = LevelBlockModel::new(500.0, 500.0, 100.0, 100.0);
+        // Orbiting its own centre
+        block.motion = Some(LevelMotionModel {
+            kind: LevelMotionKind::Orbit,
+            points: vec![LevelPointModel { x: 550.0, y: 550.0 }],
+            speed: 100.0,
+            phase: 0.0
+        });
+        level.danger_blocks.push(block);
+        let mut circle = LevelCircleModel::new(300.0, 300.0, 20.0);
+        circle.motion = Some(LevelMotionModel {
+            kind: LevelMotionKind::Linear,
+            points: vec![LevelPointModel { x: 300.0, y: 600.0 }],
+            speed: 100.0,
+            phase: 0.0
+        });
+        level.danger_circles.push(circle);
+        assert_eq!(validate(&level), vec![LevelIssue::StillBlock { index: 0 }]);
+    }
+
+    #[test]
+    fn flags_bars() {
+        let mut level = open_level();
+        // Sweeps through the goal, but only counts where it starts
+        level.danger_bars.push(LevelBarModel::new(1150.0, 300.0, 300.0, 20.0, 90.0, 0.0));
+        level.danger_bars.push(LevelBarModel::new(1000.0, 100.0, 300.0, 20.0, 90.0, 90.0));
+        level.danger_bars.push(LevelBarModel::new(600.0, 600.0, 0.0, 20.0, 90.0, 0.0));
+        assert_eq!(validate(&level), vec![LevelIssue::EmptyBar { index: 2 }]);
+
+        level.danger_bars[0].angle = 90.0;
+        assert_eq!(validate(&level)[0], LevelIssue::GoalBlocked);
+    }
+
+    #[test]
+    fn flags_pulses_that_never_switch_on() {
+        let mut level = open_level();
+        // Off when the level starts, so the player isn't blocked
+        let mut block = LevelBlockModel::new(50.0, 350.0, 100.0, 100.0);
+        block.pulse = Some(LevelPulseModel { on: 1000.0, off: 1000.0, phase: 1000.0, grow: 0.0 });
+        level.danger_blocks.push(block);
+        let mut circle = LevelCircleModel::new(600.0, 400.0, 50.0);
+        circle.pulse = Some(LevelPulseModel { on: 0.0, off: 1000.0, phase: 0.0, grow: 0.0 });
+        level.danger_circles.push(circle);
+        assert_eq!(validate(&level), vec![LevelIssue::DarkCircle { index: 0 }]);
+
+        level.danger_blocks[0].pulse.as_mut().unwrap().phase = 0.0;
+        assert_eq!(validate(&level)[0], LevelIssue::PlayerBlocked);
+    }
+
+    #[test]
+    fn flags_polygons() {
+        let mut level = open_level();
+        level.danger_polygons.push(LevelPolygonModel::new(&[(500.0, 500.0), (600.0, 500.0), (550.0, 600.0)]));
+        level.danger_polygons.push(LevelPolygonModel::new(&[(500.0, 500.0), (600.0, 500.0)]));
+        level.danger_polygons.push(LevelPolygonModel::new(&[(500.0, 500.0), (600.0, 500.0), (700.0, 500.0)]));
+        level.danger_polygons.push(LevelPolygonModel::new(&[(300.0, 300.0), (400.0, 400.0), (400.0, 300.0), (300.0, 450.0)]));
+        assert_eq!(validate(&level), vec![
+            LevelIssue::EmptyPolygon { index: 1 },
+            LevelIssue::EmptyPolygon { index: 2 },
+            LevelIssue::CrossedPolygon { index: 3 }
+        ]);
+
+        // Round the goal, but only inside the outline counts
+        level.danger_polygons.truncate(1);
+        level.danger_polygons.push(LevelPolygonModel::new(&[(1050.0, 300.0), (1250.0, 300.0), (1250.0, 400.0)]));
+        assert_eq!(validate(&level), vec![]);
+        level.danger_polygons[1].points[2].y = 600.0;
+        assert_eq!(validate(&level), vec![LevelIssue::GoalBlocked]);
+    }
+
+    #[test]
+    fn flags_walls() {
+        let mut level = open_level();
+        level.walls.push(LevelWallModel::new(1100.0, 350.0, 100.0, 100.0));
+        level.walls.push(LevelWallModel::new(500.0, 0.0, 0.0, 400.0));
+        assert_eq!(validate(&level), vec![LevelIssue::GoalBlocked, LevelIssue::EmptyWall { index: 1 }]);
+
+        // Drawn up and to the left, but still where it would be once normalised
+        level.walls[0] = LevelWallModel::new(1200.0, 450.0, -100.0, -100.0);
+        assert_eq!(validate(&level), vec![
+            LevelIssue::GoalBlocked,
+            LevelIssue::NegativeWall { index: 0 },
+            LevelIssue::EmptyWall { index: 1 }
+        ]);
+
+        // Touching is fine for a wall, but not starting inside one
+        level.walls[0] = LevelWallModel::new(100.0 + PLAYER_SIZE - 1.0, 0.0, 50.0, 800.0);
+        assert_eq!(validate(&level)[0], LevelIssue::PlayerBlocked);
+    }
+
+    #[test]
+    fn player_on_the_border() {
+        let mut level = open_level();
+        level.player.x = PLAYER_SIZE / 2.0;
+        assert_eq!(validate(&level), vec![LevelIssue::PlayerOutside]);
+    }
+
+    #[test]
+    fn check_shipped_files() {
+        let root = crate_root();
+        let manifest = load_manifest();
+        for entry in manifest.levels.iter() {
+            assert_eq!(check_file(&root, &entry.file, false), vec![], "{}", entry.file);
+        }
+        let credits = manifest.credits.unwrap();
+        assert_eq!(check_file(&root, &credits.file, true), vec![]);
+        assert!(check_file(&root, &credits.file, false).contains(&LevelIssue::GoalOutside));
+
+        let missing = check_file(&root, "assets/levels/missing.json", false);
+        assert!(matches!(missing[..], [LevelIssue::Unreadable(_)]));
+        let not_a_level = check_file(&root, DEFAULT_MANIFEST, false);
+        assert!(matches!(not_a_level[..], [LevelIssue::Unreadable(_)]));
+    }
+
+    #[test]
+    fn solves_levels_that_are_only_missing_art() {
+        let root = std::env::temp_dir().join("validator_missing_art");
+        fs::create_dir_all(&root).unwrap();
+        let mut level = open_level();
+        level.images.push(LevelImageModel { filename: "./assets/images/missing.png".to_string(), x: 0.0, y: 0.0 });
+        // Floor to ceiling, so there is no way through
+        level.walls.push(LevelWallModel::new(600.0, 0.0, 40.0, 800.0));
+        fs::write(root.join("level.json"), serde_json::to_string(&level).unwrap()).unwrap();
+
+        assert_eq!(check_file(&root, "level.json", false), vec![
+            LevelIssue::MissingImage { filename: "./assets/images/missing.png".to_string() },
+            LevelIssue::Unsolvable(SolveError::GoalUnreachable)
+        ]);
+    }
+}
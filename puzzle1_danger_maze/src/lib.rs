@@ -0,0 +1,19 @@
+// Everything the game and the level builder are made of. The web app in
+// main.rs and the native tools in src/bin both build on this.
+pub mod edit_history;
+pub mod game_control;
+pub mod game_components;
+pub mod game_state;
+pub mod geometry;
+pub mod level_builder;
+pub mod level_select;
+pub mod motion;
+pub mod progress;
+pub mod pulse;
+pub mod results;
+pub mod simulation;
+pub mod snap;
+#[cfg(test)]
+pub(crate) mod test_support;
+pub mod utils;
+pub mod levels;
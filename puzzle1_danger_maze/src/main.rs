@@ -1,16 +1,16 @@
 use yew::prelude::*;
 use yew_router::prelude::*;
 
-mod game_control;
-mod game_components;
-mod level_builder;
-mod utils;
-mod levels;
+use puzzle1_danger_maze::{game_control, level_builder};
 
 #[derive(Clone, Routable, PartialEq)]
 enum Route {
     #[at("/danger_maze")]
     Home,
+    #[at("/danger_maze/levels")]
+    LevelSelect,
+    #[at("/danger_maze/:level")]
+    Level { level: String },
     #[at("/level_builder")]
     LevelBuilder,
 }
@@ -20,6 +20,12 @@ fn switch(routes: Route) -> Html {
         Route::Home => html!{
             <game_control::GameControl />
         },
+        Route::LevelSelect => html!{
+            <game_control::GameControl show_menu={true} />
+        },
+        Route::Level { level } => html!{
+            <game_control::GameControl level={Some(level)} />
+        },
         Route::LevelBuilder => html! {
             <level_builder::LevelBuilder />
         }
@@ -38,3 +44,15 @@ fn app_body() -> Html {
 fn main() {
     yew::Renderer::<App>::new().render();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn level_routes() {
+        assert!(Route::recognize("/danger_maze/levels") == Some(Route::LevelSelect));
+        assert!(Route::recognize("/danger_maze/level7") == Some(Route::Level { level: "level7".to_string() }));
+        assert!(Route::recognize("/danger_maze") == Some(Route::Home));
+    }
+}
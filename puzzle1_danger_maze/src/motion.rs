@@ -0,0 +1,222 @@
+use std::f64::consts::PI;
+use std::vec::Vec;
+
+use crate::levels::level_model::{LevelMotionKind, LevelMotionModel, LevelPulseModel};
+use crate::pulse;
+use crate::utils::{self, Point};
+
+// Paths for moving hazards. Everything is worked out from the time since
+// the level started rather than stepped, so a replay always lines up.
+
+// Every corner the centre visits in one cycle, back to the start
+pub fn path_points(motion: &LevelMotionModel, home: Point<f64>) -> Vec::<Point<f64>> {
+    let mut points = vec![home];
+    points.extend(motion.points.iter().map(|p| Point::new(p.x, p.y)));
+    if motion.kind == LevelMotionKind::Linear && points.len() > 2 {
+        // Back through the middle points, the start closes the loop
+        let back: Vec::<Point<f64>> = points[1..points.len() - 1].iter().rev().copied().collect();
+        points.extend(back);
+    }
+    points
+}
+
+// Distance round one whole cycle, zero if the shape can't move
+pub fn cycle_length(motion: &LevelMotionModel, home: Point<f64>) -> f64 {
+    match motion.kind {
+        LevelMotionKind::Orbit => match motion.points.first() {
+            Some(centre) => 2.0 * PI * utils::dist_between_points(home, Point::new(centre.x, centre.y)),
+            None => 0.0
+        },
+        _ => {
+            let points = path_points(motion, home);
+            // Includes the closing leg back to the start
+            (0..points.len()).map(|i| utils::dist_between_points(points[i], points[(i + 1) % points.len()])).sum()
+        }
+    }
+}
+
+// Whether the motion actually goes anywhere
+pub fn is_still(motion: &LevelMotionModel, home: Point<f64>) -> bool {
+    motion.speed <= 0.0 || cycle_length(motion, home) <= 0.0
+}
+
+// Where the centre has moved to from home, `time` ms into the level
+pub fn offset_at(motion: &LevelMotionModel, home: Point<f64>, time: f64) -> Point<f64> {
+    let length = cycle_length(motion, home);
+    if length <= 0.0 {
+        return Point::new(0.0, 0.0);
+    }
+    let travelled = (motion.speed * time / 1000.0 + motion.phase * length).rem_euclid(length);
+
+    let at = match motion.kind {
+        LevelMotionKind::Orbit => {
+            let centre = Point::new(motion.points[0].x, motion.points[0].y);
+            let radius = utils::dist_between_points(home, centre);
+            let angle = (home.y - centre.y).atan2(home.x - centre.x) + travelled / radius;
+            Point::new(centre.x + radius * angle.cos(), centre.y + radius * angle.sin())
+        },
+        _ => point_along(&path_points(motion, home), travelled)
+    };
+    Point::new(at.x - home.x, at.y - home.y)
+}
+
+// Path and pulse of a hazard, and where they have got to. Blocks and circles
+// each hold one and say where their path is measured from.
+pub struct Animated {
+    pub motion: Option<LevelMotionModel>,
+    pub pulse: Option<LevelPulseModel>,
+    pub offset: Point<f64>,
+    // Offset as it was before the last update, for sweeping
+    pub prev_offset: Point<f64>,
+    // 0 when switched off, 1 at full size
+    pub scale: f64,
+    time: f64
+}
+
+impl Animated {
+    pub fn new(motion: Option<LevelMotionModel>, pulse: Option<LevelPulseModel>) -> Self {
+        Animated {
+            motion,
+            pulse,
+            offset: Point::new(0.0, 0.0),
+            prev_offset: Point::new(0.0, 0.0),
+            scale: 1.0,
+            time: 0.0
+        }
+    }
+
+    // Back to wherever its phase puts it when the level starts
+    pub fn restart(&mut self, home: Point<f64>) {
+        self.time = 0.0;
+        self.update(home, 0.0);
+        self.prev_offset = self.offset;
+    }
+
+    pub fn update(&mut self, home: Point<f64>, delta: f64) {
+        self.time += delta;
+        self.prev_offset = self.offset;
+        if let Some(motion) = &self.motion {
+            self.offset = offset_at(motion, home, self.time);
+        }
+        if let Some(pulse) = &self.pulse {
+            self.scale = pulse::scale_at(pulse, self.time);
+        }
+    }
+
+    pub fn is_on(&self) -> bool {
+        self.scale > 0.0
+    }
+
+    // Seen from the hazard, a sweep starts further back by however far the
+    // hazard moved, so a fast one can't jump over the player either
+    pub fn sweep_start(&self, start: &Point<f64>) -> Point<f64> {
+        Point::new(
+            start.x - (self.offset.x - self.prev_offset.x),
+            start.y - (self.offset.y - self.prev_offset.y)
+        )
+    }
+}
+
+// Walk `dist` round the closed path
+fn point_along(points: &[Point<f64>], dist: f64) -> Point<f64> {
+    let mut left = dist;
+    for i in 0..points.len() {
+        let (from, to) = (points[i], points[(i + 1) % points.len()]);
+        let leg = utils::dist_between_points(from, to);
+        if leg > 0.0 && left <= leg {
+            let t = left / leg;
+            return Point::new(from.x + (to.x - from.x) * t, from.y + (to.y - from.y) * t);
+        }
+        left -= leg;
+    }
+    points[0]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::levels::level_model::LevelPointModel;
+
+    fn motion(kind: LevelMotionKind, points: &[(f64, f64)]) -> LevelMotionModel {
+        LevelMotionModel {
+            kind,
+            points: points.iter().map(|(x, y)| LevelPointModel { x: *x, y: *y }).collect(),
+            speed: 100.0,
+            phase: 0.0
+        }
+    }
+
+    fn close(a: Point<f64>, b: Point<f64>) -> bool {
+        utils::dist_between_points(a, b) < 1e-6
+    }
+
+    #[test]
+    fn linear_goes_there_and_back() {
+        let home = Point::new(100.0, 100.0);
+        let there = motion(LevelMotionKind::Linear, &[(300.0, 100.0)]);
+        assert_eq!(cycle_length(&there, home), 400.0);
+        assert!(close(offset_at(&there, home, 1000.0), Point::new(100.0, 0.0)));
+        assert!(close(offset_at(&there, home, 3000.0), Point::new(100.0, 0.0)));
+        assert!(close(offset_at(&there, home, 4000.0), Point::new(0.0, 0.0)));
+
+        // Middle points are visited on the way back too
+        let bent = motion(LevelMotionKind::Linear, &[(200.0, 100.0), (200.0, 200.0)]);
+        assert_eq!(path_points(&bent, home).len(), 4);
+        assert_eq!(cycle_length(&bent, home), 400.0);
+        assert!(close(offset_at(&bent, home, 3500.0), Point::new(50.0, 0.0)));
+    }
+
+    #[test]
+    fn loop_closes_the_shape() {
+        let home = Point::new(0.0, 0.0);
+        let mut square = motion(LevelMotionKind::Loop, &[(100.0, 0.0), (100.0, 100.0), (0.0, 100.0)]);
+        assert_eq!(cycle_length(&square, home), 400.0);
+        assert!(close(offset_at(&square, home, 1500.0), Point::new(100.0, 50.0)));
+        assert!(close(offset_at(&square, home, 4000.0), home));
+
+        // A quarter of the phase is a quarter of the way round
+        square.phase = 0.25;
+        assert!(close(offset_at(&square, home, 0.0), Point::new(100.0, 0.0)));
+    }
+
+    #[test]
+    fn orbit_keeps_its_radius() {
+        let home = Point::new(200.0, 100.0);
+        let orbit = motion(LevelMotionKind::Orbit, &[(100.0, 100.0)]);
+        let length = cycle_length(&orbit, home);
+        let quarter = length / 4.0 / orbit.speed * 1000.0;
+        // y is down, so a positive angle goes clockwise on screen
+        assert!(close(offset_at(&orbit, home, quarter), Point::new(-100.0, 100.0)));
+        for time in [0.0, 123.0, 4567.0] {
+            let offset = offset_at(&orbit, home, time);
+            let at = Point::new(home.x + offset.x, home.y + offset.y);
+            assert!((utils::dist_between_points(at, Point::new(100.0, 100.0)) - 100.0).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn motions_that_go_nowhere() {
+        let home = Point::new(100.0, 100.0);
+        assert!(is_still(&motion(LevelMotionKind::Linear, &[]), home));
+        assert!(is_still(&motion(LevelMotionKind::Orbit, &[(100.0, 100.0)]), home));
+        let mut stopped = motion(LevelMotionKind::Loop, &[(200.0, 200.0)]);
+        stopped.speed = 0.0;
+        assert!(is_still(&stopped, home));
+        assert_eq!(offset_at(&motion(LevelMotionKind::Linear, &[]), home, 500.0), Point::new(0.0, 0.0));
+    }
+
+    #[test]
+    fn animated_sweeps_from_where_it_was() {
+        let home = Point::new(100.0, 100.0);
+        let mut anim = Animated::new(Some(motion(LevelMotionKind::Linear, &[(300.0, 100.0)])), None);
+        anim.restart(home);
+        anim.update(home, 500.0);
+        assert!(close(anim.offset, Point::new(50.0, 0.0)));
+        // The hazard moved 50 right, so the sweep starts 50 further left
+        assert!(close(anim.sweep_start(&Point::new(200.0, 200.0)), Point::new(150.0, 200.0)));
+        assert!(anim.is_on());
+
+        anim.restart(home);
+        assert_eq!(anim.offset, anim.prev_offset);
+    }
+}
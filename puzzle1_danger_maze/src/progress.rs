@@ -0,0 +1,174 @@
+use serde::*;
+use std::collections::HashMap;
+use std::vec::Vec;
+use web_sys::window;
+
+use crate::levels::manifest::LevelManifest;
+
+pub const PROGRESS_KEY: &str = "danger_maze_progress";
+
+// What the player has done so far, keyed by the level ids in the manifest
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "snake_case", default)]
+pub struct Progress {
+    pub unlocked: Vec::<String>,
+    // Best completion time per level, in ms
+    pub best_times: HashMap<String, f64>,
+    pub total_deaths: u32
+}
+
+impl Progress {
+    pub fn unlock(&mut self, id: &str) {
+        if !self.is_unlocked(id) {
+            self.unlocked.push(id.to_string());
+        }
+    }
+
+    pub fn is_unlocked(&self, id: &str) -> bool {
+        self.unlocked.iter().any(|unlocked| unlocked == id)
+    }
+
+    pub fn best_time(&self, id: &str) -> Option<f64> {
+        self.best_times.get(id).copied()
+    }
+
+    // Returns true if this beat the previous best
+    pub fn record_win(&mut self, id: &str, time: f64) -> bool {
+        self.unlock(id);
+        match self.best_time(id) {
+            Some(best) if best <= time => false,
+            _ => {
+                self.best_times.insert(id.to_string(), time);
+                true
+            }
+        }
+    }
+
+    pub fn record_death(&mut self) {
+        self.total_deaths += 1;
+    }
+
+    // Furthest level in the manifest that has been reached, never the credits
+    pub fn resume_index(&self, manifest: &LevelManifest) -> usize {
+        manifest.levels.iter()
+            .rposition(|entry| self.is_unlocked(&entry.id))
+            .unwrap_or(0)
+    }
+}
+
+pub trait ProgressStore {
+    fn load(&self) -> Progress;
+    fn save(&mut self, progress: &Progress);
+}
+
+// Keeps progress between visits in the browser's localStorage
+pub struct LocalStorageProgress {
+    key: String
+}
+
+impl LocalStorageProgress {
+    pub fn new(key: &str) -> Self {
+        LocalStorageProgress {
+            key: key.to_string()
+        }
+    }
+
+    fn storage() -> Option<web_sys::Storage> {
+        window()?.local_storage().ok()?
+    }
+}
+
+impl ProgressStore for LocalStorageProgress {
+    fn load(&self) -> Progress {
+        // Anything missing or unreadable just starts afresh
+        Self::storage()
+            .and_then(|storage| storage.get_item(&self.key).ok()?)
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&mut self, progress: &Progress) {
+        if let (Some(storage), Ok(data)) = (Self::storage(), serde_json::to_string(progress)) {
+            let _ = storage.set_item(&self.key, &data);
+        }
+    }
+}
+
+// Native stand-in for tests and tools
+#[derive(Default)]
+pub struct MemoryProgress {
+    progress: Progress
+}
+
+impl ProgressStore for MemoryProgress {
+    fn load(&self) -> Progress {
+        self.progress.clone()
+    }
+
+    fn save(&mut self, progress: &Progress) {
+        self.progress = progress.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::levels::manifest::LevelEntry;
+
+    fn manifest() -> LevelManifest {
+        let entry = |id: &str| LevelEntry {
+            id: id.to_string(),
+            title: id.to_string(),
+            file: format!("{}.json", id)
+        };
+        LevelManifest {
+            name: "Test Pack".to_string(),
+            levels: vec![entry("one"), entry("two"), entry("three")],
+            credits: Some(entry("end"))
+        }
+    }
+
+    #[test]
+    fn best_time_only_improves() {
+        let mut progress = Progress::default();
+        assert!(progress.record_win("one", 5000.0));
+        assert!(!progress.record_win("one", 6000.0));
+        assert!(progress.record_win("one", 4000.0));
+        assert_eq!(progress.best_time("one"), Some(4000.0));
+        assert_eq!(progress.best_time("two"), None);
+    }
+
+    #[test]
+    fn resumes_from_furthest_level() {
+        let manifest = manifest();
+        let mut progress = Progress::default();
+        assert_eq!(progress.resume_index(&manifest), 0);
+
+        progress.unlock("one");
+        progress.unlock("three");
+        progress.unlock("end");
+        assert_eq!(progress.resume_index(&manifest), 2);
+    }
+
+    #[test]
+    fn store_round_trip() {
+        let mut store = MemoryProgress::default();
+        let mut progress = store.load();
+        progress.record_death();
+        progress.record_death();
+        progress.record_win("two", 1234.0);
+        store.save(&progress);
+
+        let loaded = store.load();
+        assert_eq!(loaded.total_deaths, 2);
+        assert!(loaded.is_unlocked("two"));
+        assert_eq!(loaded, progress);
+    }
+
+    #[test]
+    fn old_saves_still_load() {
+        let progress: Progress = serde_json::from_str(r#"{"unlocked": ["one"]}"#).unwrap();
+        assert!(progress.is_unlocked("one"));
+        assert_eq!(progress.total_deaths, 0);
+    }
+}
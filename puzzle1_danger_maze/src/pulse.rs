@@ -0,0 +1,84 @@
+use crate::levels::level_model::LevelPulseModel;
+
+// Schedules for hazards that switch on and off. Like motion.rs, worked out
+// from the time since the level started so the game and replays agree.
+
+pub fn cycle_length(pulse: &LevelPulseModel) -> f64 {
+    pulse.on.max(0.0) + pulse.off.max(0.0)
+}
+
+// Nothing to dodge, probably an on time left at zero
+pub fn never_on(pulse: &LevelPulseModel) -> bool {
+    pulse.on <= 0.0
+}
+
+// Never switches off or shrinks, so it is no different to a hazard without a pulse
+pub fn always_on(pulse: &LevelPulseModel) -> bool {
+    pulse.on > 0.0 && pulse.off <= 0.0 && pulse.grow <= 0.0
+}
+
+// How big the hazard is `time` ms into the level, from 0 (off) to 1 (full size).
+// It grows over the first `grow` ms of being on and shrinks over the last.
+pub fn scale_at(pulse: &LevelPulseModel, time: f64) -> f64 {
+    let length = cycle_length(pulse);
+    if never_on(pulse) {
+        return 0.0;
+    }
+    let at = (time + pulse.phase).rem_euclid(length);
+    if at >= pulse.on {
+        return 0.0;
+    }
+    let grow = pulse.grow.clamp(0.0, pulse.on / 2.0);
+    if grow <= 0.0 {
+        1.0
+    } else {
+        (at / grow).min((pulse.on - at) / grow).min(1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pulse(on: f64, off: f64, phase: f64, grow: f64) -> LevelPulseModel {
+        LevelPulseModel { on, off, phase, grow }
+    }
+
+    #[test]
+    fn blinks_on_and_off() {
+        let blink = pulse(1000.0, 500.0, 0.0, 0.0);
+        assert_eq!(scale_at(&blink, 0.0), 1.0);
+        assert_eq!(scale_at(&blink, 999.0), 1.0);
+        assert_eq!(scale_at(&blink, 1000.0), 0.0);
+        assert_eq!(scale_at(&blink, 1499.0), 0.0);
+        assert_eq!(scale_at(&blink, 1500.0), 1.0);
+
+        // Phase moves the schedule forward
+        let late = pulse(1000.0, 500.0, 1200.0, 0.0);
+        assert_eq!(scale_at(&late, 0.0), 0.0);
+        assert_eq!(scale_at(&late, 300.0), 1.0);
+    }
+
+    #[test]
+    fn grows_and_shrinks() {
+        let breathe = pulse(1000.0, 0.0, 0.0, 250.0);
+        assert_eq!(scale_at(&breathe, 125.0), 0.5);
+        assert_eq!(scale_at(&breathe, 500.0), 1.0);
+        assert_eq!(scale_at(&breathe, 875.0), 0.5);
+
+        // Can't spend longer growing than half the time it is on
+        let quick = pulse(100.0, 100.0, 0.0, 1000.0);
+        assert_eq!(scale_at(&quick, 50.0), 1.0);
+        assert_eq!(scale_at(&quick, 25.0), 0.5);
+    }
+
+    #[test]
+    fn always_and_never() {
+        assert_eq!(scale_at(&pulse(500.0, 0.0, 0.0, 0.0), 12345.0), 1.0);
+        assert!(always_on(&pulse(500.0, 0.0, 0.0, 0.0)));
+        assert!(!always_on(&pulse(500.0, 0.0, 0.0, 100.0)));
+        assert!(!always_on(&pulse(500.0, 10.0, 0.0, 0.0)));
+        assert!(never_on(&pulse(0.0, 500.0, 0.0, 0.0)));
+        assert_eq!(scale_at(&pulse(0.0, 500.0, 0.0, 0.0), 100.0), 0.0);
+    }
+}
@@ -0,0 +1,140 @@
+use wasm_bindgen::JsValue;
+use web_sys::CanvasRenderingContext2d;
+use std::vec::Vec;
+
+use crate::utils;
+
+pub struct LevelResult {
+    pub title: String,
+    // Completion time, in ms
+    pub time: f64,
+    pub resets: u32,
+    // Best time from before this run, if there was one
+    pub previous_best: Option<f64>,
+    pub new_best: bool
+}
+
+// Everything completed since the run started, for the table at the end
+#[derive(Default)]
+pub struct RunResults {
+    pub levels: Vec::<LevelResult>
+}
+
+const TABLE_LEFT: f64 = 140.0;
+const TABLE_TOP: f64 = 120.0;
+const ROW_HEIGHT: f64 = 48.0;
+const COLUMNS: [f64; 4] = [0.0, 420.0, 620.0, 780.0];
+
+impl RunResults {
+    pub fn new() -> Self {
+        RunResults {
+            levels: Vec::new()
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.levels.clear();
+    }
+
+    pub fn push(&mut self, result: LevelResult) {
+        self.levels.push(result);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+
+    pub fn total_time(&self) -> f64 {
+        self.levels.iter().map(|l| l.time).sum()
+    }
+
+    pub fn total_resets(&self) -> u32 {
+        self.levels.iter().map(|l| l.resets).sum()
+    }
+
+    pub fn improvements(&self) -> usize {
+        self.levels.iter().filter(|l| l.new_best && l.previous_best.is_some()).count()
+    }
+
+    fn best_str(result: &LevelResult) -> String {
+        match (result.new_best, result.previous_best) {
+            (true, Some(best)) => format!("NEW BEST -{:.2}s", (best - result.time) / 1000.0),
+            (true, None) => "NEW BEST".to_string(),
+            (false, Some(best)) => format!("{}s", utils::format_time(best)),
+            (false, None) => "".to_string()
+        }
+    }
+
+    pub fn render(&self, ctx: &mut CanvasRenderingContext2d) {
+        ctx.set_fill_style(&JsValue::from("rgba(0, 0, 0, 0.6)"));
+        ctx.fill_rect(TABLE_LEFT - 40.0, TABLE_TOP - 80.0, 1080.0, ROW_HEIGHT * (self.levels.len() as f64 + 4.0) + 60.0);
+
+        ctx.set_font("48px arial");
+        utils::drop_shadow_string(ctx, "Results".to_string(), TABLE_LEFT, TABLE_TOP - 20.0);
+
+        ctx.set_font("28px arial");
+        let mut y = TABLE_TOP + ROW_HEIGHT;
+        let header = ["Level", "Time", "Resets", "Best"];
+        for (col, text) in COLUMNS.iter().zip(header.iter()) {
+            utils::drop_shadow_string(ctx, text.to_string(), TABLE_LEFT + col, y);
+        }
+
+        for result in self.levels.iter() {
+            y += ROW_HEIGHT;
+            let row = [
+                result.title.clone(),
+                format!("{}s", utils::format_time(result.time)),
+                result.resets.to_string(),
+                Self::best_str(result)
+            ];
+            for (col, text) in COLUMNS.iter().zip(row) {
+                utils::drop_shadow_string(ctx, text, TABLE_LEFT + col, y);
+            }
+        }
+
+        y += ROW_HEIGHT * 1.5;
+        let total = [
+            "Total".to_string(),
+            format!("{}s", utils::format_time(self.total_time())),
+            self.total_resets().to_string(),
+            format!("{} improved", self.improvements())
+        ];
+        for (col, text) in COLUMNS.iter().zip(total) {
+            utils::drop_shadow_string(ctx, text, TABLE_LEFT + col, y);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(time: f64, resets: u32, previous_best: Option<f64>, new_best: bool) -> LevelResult {
+        LevelResult {
+            title: "Level".to_string(),
+            time,
+            resets,
+            previous_best,
+            new_best
+        }
+    }
+
+    #[test]
+    fn totals() {
+        let mut results = RunResults::new();
+        results.push(result(1500.0, 2, None, true));
+        results.push(result(2500.0, 0, Some(3000.0), true));
+        results.push(result(4000.0, 1, Some(3500.0), false));
+        assert_eq!(results.total_time(), 8000.0);
+        assert_eq!(results.total_resets(), 3);
+        // A first clear isn't an improvement on anything
+        assert_eq!(results.improvements(), 1);
+    }
+
+    #[test]
+    fn best_column() {
+        assert_eq!(RunResults::best_str(&result(2500.0, 0, Some(3000.0), true)), "NEW BEST -0.50s");
+        assert_eq!(RunResults::best_str(&result(2500.0, 0, None, true)), "NEW BEST");
+        assert_eq!(RunResults::best_str(&result(4000.0, 0, Some(3500.0), false)), "003.50s");
+    }
+}
@@ -0,0 +1,449 @@
+use std::vec::Vec;
+
+use crate::game_components::{danger_bar::DangerBar, danger_block::DangerBlock, danger_circle::DangerCircle, danger_polygon::DangerPolygon, goal::Goal, player::Player, wall::Wall};
+use crate::game_control::{GAME_HEIGHT, GAME_WIDTH};
+use crate::geometry;
+use crate::levels::level_model::LevelModel;
+use crate::utils::{Point, Rect};
+
+// Fixed step the game is advanced by, in ms
+pub const SIM_STEP: f64 = 1000.0 / 60.0;
+// Never try to catch up more than this in one go (e.g. after the tab was hidden)
+pub const SIM_MAX_CATCH_UP: f64 = 250.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PointerInput {
+    Down(f64, f64),
+    Move(f64, f64),
+    Up(f64, f64)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SimEvent {
+    Reset,
+    Won
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SimReport {
+    pub resets: u32,
+    pub won: bool,
+    pub elapsed: f64
+}
+
+// All the rules of the maze, with no dependency on the browser so it
+// can be driven by GameControl or by native tests
+pub struct Simulation {
+    pub player: Player,
+    pub goal: Goal,
+    pub blocks: Vec::<DangerBlock>,
+    pub circles: Vec::<DangerCircle>,
+    pub bars: Vec::<DangerBar>,
+    pub polygons: Vec::<DangerPolygon>,
+    pub walls: Vec::<Wall>,
+    pointer: Point<f64>,
+    elapsed: f64,
+    resets: u32,
+    started: bool,
+    won: bool
+}
+
+impl Simulation {
+    pub fn new(level: &LevelModel) -> Self {
+        let mut blocks: Vec::<DangerBlock> = level.danger_blocks.iter().map(DangerBlock::from_model).collect();
+        blocks.iter_mut().for_each(|b| b.restart());
+        let mut circles: Vec::<DangerCircle> = level.danger_circles.iter().map(DangerCircle::from_model).collect();
+        circles.iter_mut().for_each(|c| c.restart());
+        let mut bars: Vec::<DangerBar> = level.danger_bars.iter().map(DangerBar::from_model).collect();
+        bars.iter_mut().for_each(|b| b.restart());
+
+        Simulation {
+            player: Player::new(level.player.x, level.player.y),
+            goal: Goal::new(level.goal.x, level.goal.y),
+            blocks,
+            circles,
+            bars,
+            polygons: level.danger_polygons.iter().map(DangerPolygon::from_model).collect(),
+            walls: level.walls.iter().map(Wall::from_model).collect(),
+            pointer: Point::new(level.player.x, level.player.y),
+            elapsed: 0.0,
+            resets: 0,
+            started: false,
+            won: false
+        }
+    }
+
+    // Feed the whole input stream through, one fixed step per input
+    pub fn run(level: &LevelModel, inputs: &[PointerInput], step: f64) -> SimReport {
+        let mut sim = Simulation::new(level);
+        for input in inputs.iter() {
+            sim.handle_input(*input);
+            sim.step(step);
+        }
+        sim.report()
+    }
+
+    // Returns true if the input picked up the player
+    pub fn handle_input(&mut self, input: PointerInput) -> bool {
+        match input {
+            PointerInput::Down(x, y) => {
+                self.pointer = Point::new(x, y);
+                if self.player.dist_from_player(x, y) < self.player.player_size() {
+                    self.player.set_moving();
+                    self.started = true;
+                    return true;
+                }
+            },
+            PointerInput::Move(x, y) => {
+                self.pointer = Point::new(x, y);
+            },
+            PointerInput::Up(_, _) => {
+                self.player.is_moving = false;
+            }
+        }
+        false
+    }
+
+    pub fn step(&mut self, delta: f64) -> Vec::<SimEvent> {
+        let mut events = Vec::new();
+        // The clock only starts once the player is first picked up
+        if self.started && !self.won {
+            self.elapsed += delta;
+        }
+
+        // Remember the way the player went so fast drags can be swept
+        let mut path = vec![self.player.loc, self.player.loc];
+        if self.player.is_moving {
+            // Walls hold the player back, it catches up once the pointer is clear of them
+            let walls: Vec::<Rect<f64>> = self.walls.iter().map(|w| w.pos).collect();
+            path = geometry::slide_circle_path(&self.player.loc, &self.pointer, self.player.player_size(), &walls);
+            self.player.loc = *path.last().unwrap_or(&self.player.loc);
+        }
+
+        self.goal.update(delta, self.won);
+        self.player.update(delta);
+        for block in self.blocks.iter_mut() {
+            block.update(delta);
+        }
+        for circle in self.circles.iter_mut() {
+            circle.update(delta);
+        }
+        for bar in self.bars.iter_mut() {
+            bar.update(delta);
+        }
+
+        if self.won {
+            return events;
+        }
+
+        if self.player_hit(&path) {
+            self.player.reset();
+            self.resets += 1;
+            events.push(SimEvent::Reset);
+        }
+
+        let win_dist = self.goal.get_dist() + self.player.player_size();
+        if self.player.dist_from_player(self.goal.circle.loc.x, self.goal.circle.loc.y) < win_dist {
+            self.won = true;
+            events.push(SimEvent::Won);
+        }
+        events
+    }
+
+    fn player_hit(&self, path: &[Point<f64>]) -> bool {
+        // Check the whole path travelled this step so a quick flick
+        // can't skip over a thin block or a small circle. Sliding along
+        // a wall bends it, so each straight part is checked in turn.
+        let player_size = self.player.player_size();
+        let loc = self.player.loc;
+        for part in path.windows(2) {
+            let (from, to) = (&part[0], &part[1]);
+            if self.blocks.iter().any(|block| block.swept_hit(from, to, player_size)) {
+                return true;
+            }
+            if self.circles.iter().any(|circle| circle.swept_hit(from, to, player_size)) {
+                return true;
+            }
+            if self.bars.iter().any(|bar| bar.swept_hit(from, to, player_size)) {
+                return true;
+            }
+            if self.polygons.iter().any(|polygon| polygon.swept_hit(from, to, player_size)) {
+                return true;
+            }
+        }
+
+        // Check bounds for player
+        loc.x < player_size || loc.x > GAME_WIDTH - player_size
+            || loc.y < player_size || loc.y > GAME_HEIGHT - player_size
+    }
+
+    pub fn elapsed(&self) -> f64 {
+        self.elapsed
+    }
+
+    pub fn resets(&self) -> u32 {
+        self.resets
+    }
+
+    pub fn has_started(&self) -> bool {
+        self.started
+    }
+
+    pub fn has_won(&self) -> bool {
+        self.won
+    }
+
+    pub fn report(&self) -> SimReport {
+        SimReport {
+            resets: self.resets,
+            won: self.won,
+            elapsed: self.elapsed
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::FRAC_PI_4;
+
+    use crate::game_components::player::PLAYER_SIZE;
+
+    use crate::levels::level_model::{LevelBarModel, LevelBlockModel, LevelCircleModel, LevelMotionKind, LevelMotionModel, LevelPointModel, LevelPulseModel, LevelWallModel};
+    use crate::test_support::{drag, load_level, load_manifest, open_level};
+    use crate::utils::Circle;
+
+    #[test]
+    fn every_level_starts_safely() {
+        let manifest = load_manifest();
+        for index in 0..manifest.len() {
+            let name = manifest.entry(index).unwrap().file.clone();
+            let level = load_level(&name);
+            let idle = vec![PointerInput::Move(0.0, 0.0); 120];
+            let report = Simulation::run(&level, &idle, SIM_STEP);
+            assert_eq!(report.resets, 0, "{} resets the player without input", name);
+            assert!(!report.won, "{} is won without input", name);
+            assert_eq!(report.elapsed, 0.0, "{} starts the clock without input", name);
+        }
+    }
+
+    #[test]
+    fn level1_drag_to_goal_wins() {
+        let level = load_level("assets/levels/level1.json");
+        let report = Simulation::run(&level, &drag((100.0, 100.0), (1150.0, 400.0), 60), SIM_STEP);
+        assert!(report.won);
+        assert_eq!(report.resets, 0);
+        assert!(report.elapsed > 0.0);
+    }
+
+    #[test]
+    fn level2_straight_line_resets() {
+        let level = load_level("assets/levels/level2.json");
+        let report = Simulation::run(&level, &drag((100.0, 100.0), (1150.0, 400.0), 60), SIM_STEP);
+        assert_eq!(report.resets, 1);
+        assert!(!report.won);
+    }
+
+    #[test]
+    fn fast_flick_through_thin_wall_resets() {
+        let mut level = open_level();
+        level.danger_blocks.push(LevelBlockModel::new(600.0, 0.0, 4.0, 800.0));
+
+        let report = Simulation::run(&level, &drag((100.0, 400.0), (1150.0, 400.0), 1), SIM_STEP);
+        assert_eq!(report.resets, 1);
+        assert!(!report.won);
+    }
+
+    #[test]
+    fn negative_size_block_still_resets() {
+        // Saved by an older builder after dragging from bottom right to top left
+        let mut level = open_level();
+        level.danger_blocks.push(LevelBlockModel::new(700.0, 800.0, -100.0, -800.0));
+
+        let report = Simulation::run(&level, &drag((100.0, 400.0), (1150.0, 400.0), 60), SIM_STEP);
+        assert_eq!(report.resets, 1);
+        assert!(!report.won);
+    }
+
+    #[test]
+    fn timer_stops_once_won() {
+        let level = load_level("assets/levels/level1.json");
+        let mut sim = Simulation::new(&level);
+        for input in drag((100.0, 100.0), (1150.0, 400.0), 10).iter() {
+            sim.handle_input(*input);
+            sim.step(SIM_STEP);
+        }
+        assert!(sim.has_won());
+        let elapsed = sim.elapsed();
+        sim.step(SIM_STEP);
+        assert_eq!(sim.elapsed(), elapsed);
+    }
+
+    fn moving_level(kind: LevelMotionKind, points: &[(f64, f64)], speed: f64) -> LevelModel {
+        let mut level = open_level();
+        let mut block = LevelBlockModel::new(600.0, 0.0, 40.0, 100.0);
+        block.motion = Some(LevelMotionModel {
+            kind,
+            points: points.iter().map(|(x, y)| LevelPointModel { x: *x, y: *y }).collect(),
+            speed,
+            phase: 0.0
+        });
+        level.danger_blocks.push(block);
+        level
+    }
+
+    #[test]
+    fn moving_block_is_hit_where_it_is() {
+        // Sweeps down through the middle of the level and back every 4s
+        let level = moving_level(LevelMotionKind::Linear, &[(620.0, 750.0)], 350.0);
+        let mut sim = Simulation::new(&level);
+        assert!(!sim.blocks[0].point_inside(620.0, 400.0));
+        for _ in 0..60 {
+            sim.step(SIM_STEP);
+        }
+        assert!(sim.blocks[0].point_inside(620.0, 400.0));
+
+        // Same steps, same place
+        let mut again = Simulation::new(&level);
+        for _ in 0..60 {
+            again.step(SIM_STEP);
+        }
+        assert_eq!(again.blocks[0].rect(), sim.blocks[0].rect());
+    }
+
+    #[test]
+    fn moving_block_resets_a_waiting_player() {
+        // 200px a step, too quick to be sure of landing on the player
+        let level = moving_level(LevelMotionKind::Orbit, &[(620.0, 400.0)], 12000.0);
+        let mut sim = Simulation::new(&level);
+        sim.handle_input(PointerInput::Down(100.0, 400.0));
+        // Wait on the left of the orbit for it to come round
+        sim.handle_input(PointerInput::Move(270.0, 400.0));
+        let mut reset = false;
+        for _ in 0..60 {
+            if sim.step(SIM_STEP).contains(&SimEvent::Reset) {
+                reset = true;
+                break;
+            }
+        }
+        assert!(reset);
+    }
+
+    #[test]
+    fn timing_past_a_moving_block() {
+        let level = moving_level(LevelMotionKind::Linear, &[(620.0, 750.0)], 350.0);
+        // Straight across at the start, while the block is still at the top
+        let report = Simulation::run(&level, &drag((100.0, 400.0), (1150.0, 400.0), 30), SIM_STEP);
+        assert!(report.won);
+        assert_eq!(report.resets, 0);
+        // Waiting around first lets it come down onto the path
+        let mut inputs = vec![PointerInput::Move(0.0, 0.0); 45];
+        inputs.extend(drag((100.0, 400.0), (1150.0, 400.0), 30));
+        let report = Simulation::run(&level, &inputs, SIM_STEP);
+        assert_eq!(report.resets, 1);
+    }
+
+    #[test]
+    fn spinning_bar_sweeps_the_player() {
+        let mut level = open_level();
+        // Half a turn a second, starting flat
+        level.danger_bars.push(LevelBarModel::new(640.0, 400.0, 400.0, 20.0, 180.0, 0.0));
+
+        // Sat just above the pivot, the bar comes round within half a second
+        let mut sim = Simulation::new(&level);
+        sim.handle_input(PointerInput::Down(100.0, 400.0));
+        sim.handle_input(PointerInput::Move(640.0, 300.0));
+        let mut steps = 0;
+        while !sim.step(SIM_STEP).contains(&SimEvent::Reset) {
+            steps += 1;
+            assert!(steps < 30, "bar never reached the player");
+        }
+
+        // Still hit when it turns a long way each step
+        level.danger_bars[0].speed = 3600.0;
+        let mut sim = Simulation::new(&level);
+        sim.handle_input(PointerInput::Down(100.0, 400.0));
+        sim.handle_input(PointerInput::Move(640.0, 230.0));
+        assert!((0..10).any(|_| sim.step(SIM_STEP).contains(&SimEvent::Reset)));
+    }
+
+    #[test]
+    fn bars_turning_far_in_one_step_still_hit() {
+        // Three quarters of a turn a step. Where the player sits, the bar is
+        // clear both before and after, but passes over it on the way round.
+        let mut bar = DangerBar::new(640.0, 400.0, 400.0, 20.0, 270.0 * 60.0, 0.0);
+        let player = Point::new(640.0 + 150.0 * FRAC_PI_4.cos(), 400.0 + 150.0 * FRAC_PI_4.sin());
+        assert!(!bar.circle_inside(&Circle::new(player.x, player.y, PLAYER_SIZE)));
+        bar.update(SIM_STEP);
+        assert!(!bar.circle_inside(&Circle::new(player.x, player.y, PLAYER_SIZE)));
+        assert!(bar.swept_hit(&player, &player, PLAYER_SIZE));
+
+        // More than a whole turn, and still only what it can reach
+        let mut bar = DangerBar::new(640.0, 400.0, 400.0, 20.0, 500.0 * 60.0, 0.0);
+        bar.update(SIM_STEP);
+        assert!(bar.swept_hit(&player, &player, PLAYER_SIZE));
+        assert!(!bar.swept_hit(&Point::new(640.0, 150.0), &Point::new(640.0, 150.0), PLAYER_SIZE));
+    }
+
+    #[test]
+    fn slipping_through_a_blinking_wall() {
+        let mut level = open_level();
+        // Floor to ceiling, off for the first second then on for a second
+        let mut wall = LevelBlockModel::new(600.0, 0.0, 100.0, 800.0);
+        wall.pulse = Some(LevelPulseModel { on: 1000.0, off: 1000.0, phase: 1000.0, grow: 0.0 });
+        level.danger_blocks.push(wall);
+
+        let report = Simulation::run(&level, &drag((100.0, 400.0), (1150.0, 400.0), 30), SIM_STEP);
+        assert!(report.won);
+        assert_eq!(report.resets, 0);
+        // Too late, it's back on
+        let mut inputs = vec![PointerInput::Move(0.0, 0.0); 70];
+        inputs.extend(drag((100.0, 400.0), (1150.0, 400.0), 30));
+        let report = Simulation::run(&level, &inputs, SIM_STEP);
+        assert!(!report.won);
+        assert_eq!(report.resets, 1);
+    }
+
+    #[test]
+    fn walls_hold_the_player_back() {
+        let mut level = open_level();
+        // Down from the top, stopping short of the bottom
+        level.walls.push(LevelWallModel::new(600.0, 0.0, 40.0, 600.0));
+
+        // Dragged straight at it, the player stops against it rather than resetting
+        let mut sim = Simulation::new(&level);
+        for input in drag((100.0, 400.0), (1150.0, 400.0), 30) {
+            sim.handle_input(input);
+            assert!(sim.step(SIM_STEP).is_empty());
+        }
+        assert_eq!(sim.player.loc, Point::new(580.0, 400.0));
+
+        // Lagging behind the pointer, it slides down the wall and catches up
+        sim.handle_input(PointerInput::Down(580.0, 400.0));
+        sim.handle_input(PointerInput::Move(700.0, 700.0));
+        let mut steps = 0;
+        while sim.player.loc != Point::new(700.0, 700.0) {
+            assert!(sim.step(SIM_STEP).is_empty());
+            assert!(sim.player.loc.x <= 580.0 || sim.player.loc.y >= 600.0);
+            steps += 1;
+            assert!(steps < 10, "player never caught up");
+        }
+        assert_eq!(sim.report().resets, 0);
+    }
+
+    #[test]
+    fn hazards_are_swept_along_the_slide() {
+        let mut level = open_level();
+        level.player.x = 580.0;
+        level.player.y = 100.0;
+        level.walls.push(LevelWallModel::new(600.0, 0.0, 40.0, 600.0));
+        // Beside the wall, well away from a straight line to where the player ends up
+        level.danger_circles.push(LevelCircleModel::new(552.0, 400.0, 10.0));
+
+        // One step slides down the wall and past the circle before coming off the end
+        let mut sim = Simulation::new(&level);
+        sim.handle_input(PointerInput::Down(580.0, 100.0));
+        sim.handle_input(PointerInput::Move(1000.0, 700.0));
+        assert_eq!(sim.step(SIM_STEP), vec![SimEvent::Reset]);
+    }
+}
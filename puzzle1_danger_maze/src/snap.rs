@@ -0,0 +1,133 @@
+use std::vec::Vec;
+
+use crate::utils::{Point, Rect};
+
+pub const GRID_SIZES: [f64; 3] = [10.0, 20.0, 40.0];
+// How close, in px, a point has to be to a guide to jump onto it
+pub const GUIDE_DISTANCE: f64 = 8.0;
+
+// Snapping options for the level builder
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SnapSettings {
+    pub grid: bool,
+    pub grid_index: usize,
+    pub guides: bool,
+    // Also snap the player and goal, not just the hazards
+    pub markers: bool
+}
+
+// Where a point ended up, and which guides (if any) it is sitting on
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Snapped {
+    pub point: Point<f64>,
+    pub guide_x: Option<f64>,
+    pub guide_y: Option<f64>
+}
+
+impl Default for SnapSettings {
+    fn default() -> Self {
+        SnapSettings {
+            grid: true,
+            grid_index: 1,
+            guides: true,
+            markers: false
+        }
+    }
+}
+
+impl SnapSettings {
+    pub fn grid_size(&self) -> f64 {
+        GRID_SIZES[self.grid_index % GRID_SIZES.len()]
+    }
+
+    pub fn next_grid_size(&mut self) {
+        self.grid_index = (self.grid_index + 1) % GRID_SIZES.len();
+    }
+
+    // Guides win over the grid when both are in range
+    pub fn snap(&self, pt: Point<f64>, blocks: &[Rect<f64>]) -> Snapped {
+        let mut snapped = Snapped {
+            point: pt,
+            guide_x: None,
+            guide_y: None
+        };
+        if self.grid {
+            snapped.point = Point::new(snap_to_grid(pt.x, self.grid_size()), snap_to_grid(pt.y, self.grid_size()));
+        }
+        if self.guides {
+            let (xs, ys) = guide_lines(blocks);
+            snapped.guide_x = nearest_guide(pt.x, &xs);
+            snapped.guide_y = nearest_guide(pt.y, &ys);
+            if let Some(x) = snapped.guide_x {
+                snapped.point.x = x;
+            }
+            if let Some(y) = snapped.guide_y {
+                snapped.point.y = y;
+            }
+        }
+        snapped
+    }
+}
+
+pub fn snap_to_grid(value: f64, size: f64) -> f64 {
+    (value / size).round() * size
+}
+
+// The edges and centre of every block, as vertical (x) and horizontal (y) lines
+pub fn guide_lines(blocks: &[Rect<f64>]) -> (Vec::<f64>, Vec::<f64>) {
+    let mut xs = Vec::<f64>::new();
+    let mut ys = Vec::<f64>::new();
+    for b in blocks.iter() {
+        xs.extend([b.loc.x, b.loc.x + b.width / 2.0, b.loc.x + b.width]);
+        ys.extend([b.loc.y, b.loc.y + b.height / 2.0, b.loc.y + b.height]);
+    }
+    (xs, ys)
+}
+
+fn nearest_guide(value: f64, guides: &[f64]) -> Option<f64> {
+    guides.iter()
+        .copied()
+        .filter(|g| (g - value).abs() <= GUIDE_DISTANCE)
+        .min_by(|a, b| (a - value).abs().total_cmp(&(b - value).abs()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grid_rounds_to_nearest() {
+        assert_eq!(snap_to_grid(133.0, 20.0), 140.0);
+        assert_eq!(snap_to_grid(129.0, 20.0), 120.0);
+        assert_eq!(snap_to_grid(-14.0, 10.0), -10.0);
+
+        let mut settings = SnapSettings::default();
+        assert_eq!(settings.grid_size(), 20.0);
+        settings.next_grid_size();
+        settings.next_grid_size();
+        assert_eq!(settings.grid_size(), 10.0);
+    }
+
+    #[test]
+    fn guides_beat_the_grid() {
+        let settings = SnapSettings::default();
+        let blocks = [Rect::new(103.0, 200.0, 50.0, 50.0)];
+
+        // x is near the block's left edge, y is nowhere near a guide
+        let snapped = settings.snap(Point::new(107.0, 391.0), &blocks);
+        assert_eq!(snapped.point, Point::new(103.0, 400.0));
+        assert_eq!(snapped.guide_x, Some(103.0));
+        assert_eq!(snapped.guide_y, None);
+
+        // Centre lines count too
+        let snapped = settings.snap(Point::new(300.0, 222.0), &blocks);
+        assert_eq!(snapped.point.y, 225.0);
+    }
+
+    #[test]
+    fn everything_off_leaves_the_point() {
+        let settings = SnapSettings { grid: false, guides: false, ..SnapSettings::default() };
+        let pt = Point::new(107.3, 391.9);
+        assert_eq!(settings.snap(pt, &[Rect::new(100.0, 390.0, 5.0, 5.0)]).point, pt);
+    }
+}
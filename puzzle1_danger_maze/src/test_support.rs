@@ -0,0 +1,51 @@
+use std::fs;
+use std::path::PathBuf;
+use std::vec::Vec;
+
+use crate::levels::level_model::LevelModel;
+use crate::levels::manifest::{LevelManifest, DEFAULT_MANIFEST};
+use crate::simulation::PointerInput;
+
+// Levels and inputs shared by the native tests of the simulation, solver and
+// validator.
+
+// Paths in the manifest are relative to the crate root, as served by trunk
+pub fn crate_root() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+}
+
+pub fn load_level(path: &str) -> LevelModel {
+    let data = fs::read_to_string(crate_root().join(path)).unwrap();
+    serde_json::from_str(&data).unwrap()
+}
+
+pub fn load_manifest() -> LevelManifest {
+    let data = fs::read_to_string(crate_root().join(DEFAULT_MANIFEST)).unwrap();
+    serde_json::from_str(&data).unwrap()
+}
+
+// Nothing in the way, with the player and goal either side of the middle
+pub fn open_level() -> LevelModel {
+    let mut level = LevelModel::new();
+    level.player.x = 100.0;
+    level.player.y = 400.0;
+    level.goal.x = 1150.0;
+    level.goal.y = 400.0;
+    level
+}
+
+// The pointer moving in a straight line in `steps` moves, without the start
+pub fn moves(from: (f64, f64), to: (f64, f64), steps: usize) -> Vec::<PointerInput> {
+    (1..=steps).map(|i| {
+        let t = i as f64 / steps as f64;
+        PointerInput::Move(from.0 + (to.0 - from.0) * t, from.1 + (to.1 - from.1) * t)
+    }).collect()
+}
+
+// Pick up the player and drag it in a straight line in `steps` moves
+pub fn drag(from: (f64, f64), to: (f64, f64), steps: usize) -> Vec::<PointerInput> {
+    let mut inputs = vec![PointerInput::Down(from.0, from.1)];
+    inputs.extend(moves(from, to, steps));
+    inputs.push(PointerInput::Up(to.0, to.1));
+    inputs
+}
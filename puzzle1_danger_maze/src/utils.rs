@@ -1,6 +1,7 @@
 use wasm_bindgen::JsValue;
 use web_sys::CanvasRenderingContext2d;
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Point<T> {
     pub x: T,
     pub y: T
@@ -15,6 +16,7 @@ impl<T> Point<T> {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Rect<T> {
     pub loc: Point<T>,
     pub width: T,
@@ -31,6 +33,7 @@ impl<T> Rect<T> {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Circle<T> {
     pub loc: Point<T>,
     pub size: T,
@@ -65,6 +68,13 @@ pub fn format_time_2_digits(time: f64) -> String {
     out_str[1..].to_string()
 }
 
+// Time in ms as seconds with two decimal places e.g. 012.34
+pub fn format_time(time: f64) -> String {
+    let time_sec = (time / 1000.0).floor();
+    let time_ms = ((time - (time_sec * 1000.0)) / 10.0).floor();
+    format!("{}.{}", format_time_3_digits(time_sec), format_time_2_digits(time_ms))
+}
+
 pub fn drop_shadow_string(ctx: &mut CanvasRenderingContext2d, text: String, x: f64, y: f64) {
     ctx.set_fill_style(&JsValue::from("rgb(0,0,0)"));
     let _ = ctx.fill_text(&text, x + 3.0, y+3.0);
@@ -76,4 +86,4 @@ pub fn dist_between_points(pt1: Point<f64>, pt2: Point<f64>) -> f64 {
     let diff_x = pt1.x - pt2.x;
     let diff_y = pt1.y - pt2.y;
     ((diff_x*diff_x) + (diff_y*diff_y)).sqrt()
-}
\ No newline at end of file
+}